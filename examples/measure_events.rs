@@ -1,5 +1,6 @@
 use chrono::prelude::*;
-use opensea_stream::client::Client;
+use futures_util::StreamExt;
+use opensea_stream::client::{Client, Error};
 use opensea_stream::protocol::{Collection, Network};
 use opensea_stream::schema;
 use std::env;
@@ -10,8 +11,13 @@ async fn main() {
     let args: Vec<String> = env::args().collect();
     let api_key = &args[1];
 
-    let mut client = Client::new(Network::Mainnet, api_key).await;
-    client.subscribe(Collection::All).await;
+    let mut client = Client::new(Network::Mainnet, api_key)
+        .await
+        .expect("failed to connect");
+    client
+        .subscribe(Collection::All)
+        .await
+        .expect("failed to subscribe");
 
     let timer = Instant::now();
     let mut counter_item_listed: u64 = 0;
@@ -30,14 +36,17 @@ async fn main() {
         "listings", "sold", "transfer", "metadata", "cancel", "offer", "bid", "c_offer", "t_offer", "invalid", "revalid", "total");
 
     let mut second = Utc::now().second();
-    loop {
-        let event = client.read_event().await;
-
+    while let Some(event) = client.next().await {
         let event = match event {
-            Some(v) => v,
-            _ => {
+            Ok(v) => v,
+            Err(Error::Decode { raw, error }) => {
+                println!("failed to decode event: {}\n{}", error, raw);
                 continue;
             }
+            Err(e) => {
+                println!("connection lost: {}", e);
+                break;
+            }
         };
 
         match event.payload {
@@ -1,5 +1,3 @@
-use serde_json;
-
 use opensea_stream::schema;
 
 pub fn test_parsing() {
@@ -7,3 +5,7 @@ pub fn test_parsing() {
 
     let _json = serde_json::from_str::<schema::StreamEvent>(input).unwrap();
 }
+
+fn main() {
+    test_parsing();
+}
@@ -1,99 +1,1009 @@
+use chrono::{DateTime, Utc};
 use core::fmt::Display;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{ready, SinkExt, Stream, StreamExt};
+use rand::Rng;
 use serde::Deserialize;
-use tokio::{sync::mpsc, time::Duration};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use serde_json::value::RawValue;
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::{Arc, PoisonError, RwLock},
+    task::{Context, Poll},
+};
+use tokio::{
+    net::TcpStream,
+    sync::{broadcast, mpsc, mpsc::error::TrySendError, oneshot},
+    time::{Duration, Instant, MissedTickBehavior},
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{error::TlsError, protocol::Message, Error as WsError},
+    MaybeTlsStream, WebSocketStream,
+};
 
 use crate::{
-    protocol::{Collection, Network},
-    schema::StreamEvent,
+    protocol::{Collection, Event, Network},
+    schema::{self, StreamEvent},
 };
 
+/// Errors returned by the [`Client`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The websocket URL could not be built.
+    Url(url::ParseError),
+    /// The websocket connection could not be established.
+    Connect(Box<WsError>),
+    /// The websocket connection could not be established within the configured timeout.
+    ConnectTimeout,
+    /// The TLS handshake with the server failed.
+    Tls(TlsError),
+    /// The websocket failed after the connection was established.
+    Websocket(Box<WsError>),
+    /// The websocket has been closed.
+    SocketClosed,
+    /// The server did not reply to the configured number of heartbeats.
+    HeartbeatTimeout,
+    /// The server sent a message which does not follow the Phoenix protocol.
+    Protocol(String),
+    /// A message from the server could not be decoded, e.g. because OpenSea changed the schema
+    /// of an event. The connection is not affected.
+    Decode {
+        /// The received message.
+        raw: String,
+        /// Reason why decoding failed.
+        error: serde_json::Error,
+    },
+    /// The server replied with an error to a request for `topic`.
+    Rejected {
+        /// Topic of the request.
+        topic: String,
+        /// Body of the reply.
+        response: serde_json::Value,
+    },
+}
+
+impl Error {
+    fn from_connect(error: WsError) -> Self {
+        match error {
+            WsError::Tls(e) => Error::Tls(e),
+            e => Error::Connect(Box::new(e)),
+        }
+    }
+
+    /// Whether reconnecting cannot help, e.g. because the server rejected the API key.
+    fn is_fatal(&self) -> bool {
+        match self {
+            Error::Connect(e) => {
+                matches!(e.as_ref(), WsError::Http(response) if response.status().is_client_error())
+            }
+            _ => false,
+        }
+    }
+
+    fn from_websocket(error: WsError) -> Self {
+        match error {
+            WsError::ConnectionClosed | WsError::AlreadyClosed => Error::SocketClosed,
+            WsError::Protocol(e) => Error::Protocol(e.to_string()),
+            e => Error::Websocket(Box::new(e)),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Url(e) => write!(f, "invalid websocket url: {}", e),
+            Error::Connect(e) => write!(f, "failed to connect: {}", e),
+            Error::ConnectTimeout => write!(f, "timed out while connecting"),
+            Error::Tls(e) => write!(f, "tls error: {}", e),
+            Error::Websocket(e) => write!(f, "websocket error: {}", e),
+            Error::SocketClosed => write!(f, "websocket closed"),
+            Error::HeartbeatTimeout => write!(f, "server stopped replying to heartbeats"),
+            Error::Protocol(e) => write!(f, "protocol error: {}", e),
+            Error::Decode { error, .. } => write!(f, "failed to decode message: {}", error),
+            Error::Rejected { topic, response } => {
+                write!(f, "request for {} rejected: {}", topic, response)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Url(e) => Some(e),
+            Error::Connect(e) | Error::Websocket(e) => Some(e.as_ref()),
+            Error::Tls(e) => Some(e),
+            Error::Decode { error, .. } => Some(error),
+            Error::ConnectTimeout
+            | Error::SocketClosed
+            | Error::HeartbeatTimeout
+            | Error::Protocol(_)
+            | Error::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(e: url::ParseError) -> Self {
+        Error::Url(e)
+    }
+}
+
+/// Configuration of the automatic reconnect of the [`Client`].
+///
+/// After the connection is lost, the client waits `initial_delay` before redialing. Every failed
+/// attempt multiplies the delay by `multiplier` until `max_delay` is reached. The delay of each
+/// attempt is randomly varied by up to `jitter` (a fraction of the delay) so that many clients do
+/// not reconnect at the same time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnect attempt.
+    pub initial_delay: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_delay: Duration,
+    /// Factor the delay is multiplied with after every failed attempt.
+    pub multiplier: f64,
+    /// Fraction of the delay which is randomly added or subtracted, between `0.0` and `1.0`.
+    pub jitter: f64,
+    /// Maximum number of consecutive attempts. `None` retries forever, unless the server rejects
+    /// the handshake with a 4xx status, e.g. because the API key was revoked.
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectConfig {
+    /// A configuration which never reconnects.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Default::default()
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let base = base.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 + rand::thread_rng().gen_range(-jitter..=jitter)
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64(base * factor)
+    }
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+/// Changes of the connection state of the [`Client`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionEvent {
+    /// The connection was lost. Unless reconnecting is disabled, the client tries to reconnect.
+    Disconnected {
+        /// Description of the error which closed the connection.
+        reason: String,
+    },
+    /// The connection was established again and all subscriptions have been replayed.
+    Reconnected {
+        /// Number of attempts it took to reconnect.
+        attempts: u32,
+    },
+}
+
+/// Acknowledgement of the server for joining a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JoinReply {
+    /// The joined collection.
+    pub collection: Collection,
+    /// Body of the reply.
+    pub response: serde_json::Value,
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Builder for a [`Client`] with non-default settings.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), opensea_stream::client::Error> {
+/// use opensea_stream::{client::Client, protocol::Network};
+/// use std::time::Duration;
+///
+/// let client = Client::builder(Network::Mainnet, "api-key")
+///     .heartbeat_interval(Duration::from_secs(15))
+///     .read_buffer_size(64 * 1024)
+///     .connect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    endpoint: String,
+    api_key: String,
+    heartbeat_interval: Duration,
+    max_missed_heartbeats: u32,
+    send_buffer_size: usize,
+    read_buffer_size: usize,
+    connect_timeout: Option<Duration>,
+    reconnect: ReconnectConfig,
+    lenient: bool,
+}
+
+impl ClientBuilder {
+    /// Creates a builder for connecting to the websocket of `network`.
+    pub fn new(network: Network, api_key: &str) -> Self {
+        Self {
+            endpoint: network.to_string(),
+            api_key: api_key.to_string(),
+            heartbeat_interval: Duration::from_secs(30),
+            max_missed_heartbeats: 2,
+            send_buffer_size: 4,
+            read_buffer_size: 1024 * 8,
+            connect_timeout: None,
+            reconnect: ReconnectConfig::default(),
+            lenient: false,
+        }
+    }
+
+    /// Connects to a custom websocket URL instead of the one of the [`Network`],
+    /// e.g. a local mock server or a relay. The API key is still appended as `token` query parameter.
+    pub fn endpoint(mut self, url: &str) -> Self {
+        self.endpoint = url.to_string();
+        self
+    }
+
+    /// Interval between two heartbeats sent to the server. Defaults to 30 seconds.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Number of heartbeats which may stay unanswered before the connection is considered dead
+    /// and [`Error::HeartbeatTimeout`] triggers a reconnect. Defaults to 2, `0` disables the check.
+    ///
+    /// A heartbeat counts as missed if its reply has not arrived a full heartbeat interval after it
+    /// was sent. Replies are not awaited while the read buffer is full, since the client cannot
+    /// read them until the buffered events are consumed.
+    pub fn max_missed_heartbeats(mut self, max: u32) -> Self {
+        self.max_missed_heartbeats = max;
+        self
+    }
+
+    /// Number of outgoing messages which can be queued. Defaults to 4.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = size.max(1);
+        self
+    }
+
+    /// Number of received messages which can be queued before the client stops reading from
+    /// the websocket. Defaults to 8192. Heartbeats are still sent while the buffer is full.
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = size.max(1);
+        self
+    }
+
+    /// Maximum duration of establishing a connection, including every reconnect.
+    /// By default, there is no timeout.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// How to reconnect when the connection is lost. See [`ReconnectConfig`].
+    pub fn reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Decodes events leniently, so that fields which fail to decode do not drop the whole event.
+    /// See [`StreamEvent::from_str_lenient`]. Disabled by default.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Connects to the websocket.
+    pub async fn connect(self) -> Result<Client, Error> {
+        let mut url = url::Url::parse(&self.endpoint)?;
+        url.query_pairs_mut().append_pair("token", &self.api_key);
+
+        let ws_stream = connect(&url, self.connect_timeout).await?;
+
+        let (send_tx, send_rx) = mpsc::channel::<Request>(self.send_buffer_size);
+        let (read_tx, read_rx) = mpsc::channel::<Result<Received, Error>>(self.read_buffer_size);
+        let topics = Arc::new(RwLock::new(HashSet::new()));
+        let (events_tx, _) = broadcast::channel::<ConnectionEvent>(16);
+
+        let supervisor = Supervisor {
+            url,
+            reconnect: self.reconnect,
+            heartbeat_interval: self.heartbeat_interval,
+            max_missed_heartbeats: self.max_missed_heartbeats,
+            missed_heartbeats: 0,
+            heartbeat_sent: None,
+            connect_timeout: self.connect_timeout,
+            send_rx,
+            read_tx,
+            topics: topics.clone(),
+            events_tx: events_tx.clone(),
+            subscriptions: Vec::new(),
+            last_ref: 0,
+            pending: HashMap::new(),
+            rejoining: Vec::new(),
+        };
+        tokio::spawn(supervisor.run(ws_stream));
+
+        Ok(Client {
+            send_tx,
+            read_rx,
+            topics,
+            events_tx,
+            lenient: self.lenient,
+        })
+    }
+}
+
 pub struct Client {
-    send_tx: mpsc::Sender<PhoenixMessage>,
-    read_rx: mpsc::Receiver<String>,
+    send_tx: mpsc::Sender<Request>,
+    read_rx: mpsc::Receiver<Result<Received, Error>>,
+    /// Topics which are currently joined, shared with the [`Supervisor`].
+    topics: Arc<RwLock<HashSet<String>>>,
+    events_tx: broadcast::Sender<ConnectionEvent>,
+    lenient: bool,
 }
 
 impl Client {
-    pub async fn new(network: Network, api_key: &str) -> Self {
-        let url = url::Url::parse(&format!("{}?token={}", network, api_key)).unwrap();
+    pub async fn new(network: Network, api_key: &str) -> Result<Self, Error> {
+        Self::builder(network, api_key).connect().await
+    }
 
-        let (ws_stream, _) = connect_async(url).await.expect("Failed to connect");
-        println!("WebSocket handshake has been successfully completed");
-        let (mut ws_write, mut ws_read) = ws_stream.split();
+    /// Creates a [`ClientBuilder`] to configure the connection.
+    pub fn builder(network: Network, api_key: &str) -> ClientBuilder {
+        ClientBuilder::new(network, api_key)
+    }
 
-        let (send_tx, mut send_rx) = mpsc::channel::<PhoenixMessage>(4);
-        let (read_tx, read_rx) = mpsc::channel::<String>(1024*8);
+    /// Returns a receiver for changes of the connection state.
+    ///
+    /// Only changes which happen after this call are received.
+    pub fn connection_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events_tx.subscribe()
+    }
 
-        // handler to send messages to the websocket
-        tokio::spawn(async move {
-            while let Some(message) = send_rx.recv().await {
-                let payload = message.to_string();
-                ws_write.send(Message::binary(payload)).await.unwrap();
-            }
-        });
-
-        // handler to read messages from the websocket
-        tokio::spawn(async move {
-            while let Some(message) = ws_read.next().await {
-                let message = message.unwrap();
-                // println!("RECEIVED = {:?}", message);
-                let payload = match message {
-                    Message::Text(payload) => payload,
-                    _ => panic!("unexpected message"),
-                };
-                read_tx.send(payload).await.unwrap();
-            }
-        });
+    /// Joins the topic of `collection` and waits for the server to acknowledge it.
+    ///
+    /// The subscription is replayed automatically whenever the client reconnects.
+    /// If the server rejects the join, [`Error::Rejected`] is returned and the subscription is dropped.
+    pub async fn subscribe(&mut self, collection: Collection) -> Result<JoinReply, Error> {
+        self.join(collection, None).await
+    }
 
-        let send_heartbeat = send_tx.clone();
+    /// Joins the topic of `collection`, but only delivers events of the given types.
+    ///
+    /// Other events are dropped before their payload is decoded. Subscribing to the same
+    /// collection again replaces the filter.
+    ///
+    /// ```no_run
+    /// # async fn run(client: &mut opensea_stream::client::Client) -> Result<(), opensea_stream::client::Error> {
+    /// use opensea_stream::protocol::{Collection, Event};
+    ///
+    /// client
+    ///     .subscribe_events(Collection::All, [Event::ItemSold, Event::ItemListed])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn subscribe_events(
+        &mut self,
+        collection: Collection,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<JoinReply, Error> {
+        self.join(collection, Some(events.into_iter().collect()))
+            .await
+    }
 
-        tokio::spawn(async move {
-            loop {
-                let _ = send_heartbeat.send(PhoenixMessage::Heartbeat).await;
-                tokio::time::sleep(Duration::from_secs(30)).await;
-            }
-        });
-        println!("connected to {}", network);
+    async fn join(
+        &mut self,
+        collection: Collection,
+        events: Option<HashSet<Event>>,
+    ) -> Result<JoinReply, Error> {
+        let response = self
+            .request(PhoenixMessage::Subscribe(collection.clone()), events)
+            .await?;
+        Ok(JoinReply {
+            collection,
+            response,
+        })
+    }
 
-        Self { send_tx, read_rx }
+    /// Leaves the topic of `collection` and waits for the server to acknowledge it.
+    ///
+    /// No events of the collection are returned by [`Client::read_event`] afterwards,
+    /// even if they were already received, and the subscription is not replayed on reconnect.
+    pub async fn unsubscribe(&mut self, collection: Collection) -> Result<(), Error> {
+        self.request(PhoenixMessage::Unsubscribe(collection), None)
+            .await
+            .map(|_| ())
     }
 
-    pub async fn subscribe(&mut self, collection: Collection) {
+    /// Sends `message` and waits for the body of the reply.
+    async fn request(
+        &mut self,
+        message: PhoenixMessage,
+        events: Option<HashSet<Event>>,
+    ) -> Result<serde_json::Value, Error> {
+        let (reply_tx, reply_rx) = oneshot::channel();
         self.send_tx
-            .clone()
-            .send(PhoenixMessage::Subscribe(collection))
+            .send(Request {
+                message,
+                events,
+                reply_tx,
+            })
             .await
-            .unwrap();
+            .map_err(|_| Error::SocketClosed)?;
+        reply_rx.await.map_err(|_| Error::SocketClosed)?
     }
 
-    pub async fn read_event(&mut self) -> Option<StreamEvent> {
-        let message = self.read_rx.recv().await.unwrap();
+    /// Waits for the next message from the websocket.
+    ///
+    /// Returns `Ok(None)` for messages which are not stream events (e.g. replies to replayed joins).
+    /// A [`Error::Decode`] does not affect the connection, so reading can continue afterwards.
+    /// Once reconnecting has failed, the last connection error is returned, followed by
+    /// [`Error::SocketClosed`].
+    ///
+    /// See the [`Stream`] implementation for a version skipping messages which are not events.
+    pub async fn read_event(&mut self) -> Result<Option<StreamEvent>, Error> {
+        let message = self.recv().await?;
+        decode_event(message, self.lenient)
+    }
 
-        // println!("{:#?}", message);
-        let response = match serde_json::from_str::<PhoenixResponse>(&message) {
-            Ok(v) => v,
-            Err(_e) => {
-                // println!("{}", &message);
-                // println!("error: {}", e);
-                return None;
+    /// Like [`Client::read_event`], but only decodes the routing information of the event.
+    pub async fn read_raw_event(&mut self) -> Result<Option<RawEvent>, Error> {
+        let message = self.recv().await?;
+        decode_raw_event(message)
+    }
+
+    /// Waits for the next message which does not belong to a left topic.
+    async fn recv(&mut self) -> Result<String, Error> {
+        loop {
+            let received = self.read_rx.recv().await.ok_or(Error::SocketClosed)??;
+            if !self.is_left(&received) {
+                return Ok(received.text);
             }
+        }
+    }
+
+    /// Whether the message was queued before its topic was left.
+    fn is_left(&self, received: &Received) -> bool {
+        let topics = self.topics.read().unwrap_or_else(PoisonError::into_inner);
+        received
+            .topic
+            .as_ref()
+            .is_some_and(|topic| !topics.contains(topic))
+    }
+
+    /// Stream of the received events without decoding their payloads. See [`RawEvent`].
+    pub fn raw_events(&mut self) -> impl Stream<Item = Result<RawEvent, Error>> + '_ {
+        futures_util::stream::poll_fn(move |cx| self.poll_next_message(cx, decode_raw_event))
+    }
+
+    /// Polls for the next message which `decode` turns into an item, skipping all others.
+    fn poll_next_message<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        decode: impl Fn(String) -> Result<Option<T>, Error>,
+    ) -> Poll<Option<Result<T, Error>>> {
+        loop {
+            let message = match ready!(self.read_rx.poll_recv(cx)) {
+                Some(Ok(received)) if self.is_left(&received) => continue,
+                Some(message) => message.map(|received| received.text),
+                None => return Poll::Ready(None),
+            };
+            match message.and_then(&decode) {
+                Ok(Some(item)) => return Poll::Ready(Some(Ok(item))),
+                Ok(None) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+/// Stream of the received events.
+///
+/// Messages which are not events (e.g. replies to replayed joins) are skipped. The stream ends once
+/// reconnecting has failed, after yielding the last connection error.
+impl Stream for Client {
+    type Item = Result<StreamEvent, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let lenient = self.lenient;
+        self.get_mut()
+            .poll_next_message(cx, |message| decode_event(message, lenient))
+    }
+}
+
+fn decode_event(message: String, lenient: bool) -> Result<Option<StreamEvent>, Error> {
+    let decoded = match RawEvent::from_message(&message) {
+        Ok(Some(raw)) if lenient => raw.decode_lenient().map(Some),
+        Ok(Some(raw)) => raw.decode().map(Some),
+        Ok(None) => Ok(None),
+        Err(error) => Err(error),
+    };
+    decoded.map_err(|error| Error::Decode {
+        raw: message,
+        error,
+    })
+}
+
+fn decode_raw_event(message: String) -> Result<Option<RawEvent>, Error> {
+    RawEvent::from_message(&message).map_err(|error| Error::Decode {
+        raw: message,
+        error,
+    })
+}
+
+/// A stream event of which only the routing information has been decoded.
+///
+/// The payload is kept as raw JSON, so that events can be routed and filtered cheaply.
+/// It is decoded on demand with [`RawEvent::decode`] or [`RawEvent::decode_payload`].
+#[derive(Debug, Clone)]
+pub struct RawEvent {
+    /// Topic the event was received on, e.g. `collection:*`.
+    pub topic: String,
+    /// Type of the event, e.g. `item_listed`. See [`RawEvent::event`].
+    pub event_type: String,
+    /// Timestamp of when this message was sent to the client.
+    pub sent_at: DateTime<Utc>,
+    payload: Box<RawValue>,
+}
+
+impl RawEvent {
+    /// Decodes the routing information of a message received from the websocket.
+    ///
+    /// Returns `None` if the message is not a stream event, e.g. a reply.
+    pub fn from_message(message: &str) -> Result<Option<Self>, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Message {
+            topic: String,
+            payload: Option<Box<RawValue>>,
+        }
+
+        #[derive(Deserialize)]
+        struct Header {
+            event_type: Option<String>,
+            sent_at: Option<DateTime<Utc>>,
+        }
+
+        let message: Message = serde_json::from_str(message)?;
+        let payload = match message.payload {
+            Some(payload) => payload,
+            None => return Ok(None),
         };
+        let header: Header = serde_json::from_str(payload.get())?;
+        match (header.event_type, header.sent_at) {
+            (Some(event_type), Some(sent_at)) => Ok(Some(RawEvent {
+                topic: message.topic,
+                event_type,
+                sent_at,
+                payload,
+            })),
+            (Some(_), None) => Err(serde::de::Error::missing_field("sent_at")),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Type of the event.
+    pub fn event(&self) -> Event {
+        self.event_type
+            .parse()
+            .unwrap_or_else(|_| Event::Other(self.event_type.clone()))
+    }
+
+    /// The undecoded payload, as [described here](https://docs.opensea.io/reference/stream-api-event-schemas).
+    pub fn raw_payload(&self) -> &RawValue {
+        &self.payload
+    }
+
+    /// Decodes the whole event.
+    pub fn decode(&self) -> Result<StreamEvent, serde_json::Error> {
+        serde_json::from_str(self.payload.get())
+    }
+
+    /// Decodes the whole event, tolerating fields which cannot be decoded.
+    /// See [`StreamEvent::from_str_lenient`].
+    pub fn decode_lenient(&self) -> Result<StreamEvent, serde_json::Error> {
+        StreamEvent::from_str_lenient(self.payload.get())
+    }
+
+    /// Decodes the content of the event.
+    pub fn decode_payload(&self) -> Result<schema::Payload, serde_json::Error> {
+        serde_json::from_str(self.payload.get())
+    }
+}
+
+async fn connect(url: &url::Url, timeout: Option<Duration>) -> Result<WsStream, Error> {
+    let connecting = connect_async(url.clone());
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connecting)
+            .await
+            .map_err(|_| Error::ConnectTimeout)?,
+        None => connecting.await,
+    };
+    let (ws_stream, _) = result.map_err(Error::from_connect)?;
+    Ok(ws_stream)
+}
+
+/// Background task owning the websocket. It redials the server when the connection is lost.
+struct Supervisor {
+    url: url::Url,
+    reconnect: ReconnectConfig,
+    heartbeat_interval: Duration,
+    max_missed_heartbeats: u32,
+    /// Heartbeats of the current connection which have not been answered in time.
+    missed_heartbeats: u32,
+    /// When the last heartbeat was sent, while its reply is outstanding.
+    heartbeat_sent: Option<Instant>,
+    connect_timeout: Option<Duration>,
+    send_rx: mpsc::Receiver<Request>,
+    read_tx: mpsc::Sender<Result<Received, Error>>,
+    /// Topics of `subscriptions`, shared with the [`Client`] so that it drops queued events of
+    /// left topics.
+    topics: Arc<RwLock<HashSet<String>>>,
+    events_tx: broadcast::Sender<ConnectionEvent>,
+    /// Every collection joined so far, in the order of joining.
+    subscriptions: Vec<Subscription>,
+    /// Ref of the last message sent to the server.
+    last_ref: u64,
+    /// Requests waiting for a reply of the server, by ref.
+    pending: HashMap<u64, Request>,
+    /// Joins which were pending when the connection was lost, waiting for their replay.
+    rejoining: Vec<Request>,
+}
+
+/// A joined topic.
+struct Subscription {
+    topic: String,
+    collection: Collection,
+    /// Event types to deliver, `None` for all.
+    events: Option<HashSet<Event>>,
+}
+
+/// A message for the server, sent from the [`Client`] to the [`Supervisor`].
+struct Request {
+    message: PhoenixMessage,
+    /// Event types to deliver if the message is a join, `None` for all.
+    events: Option<HashSet<Event>>,
+    /// Receives the body of the reply of the server.
+    reply_tx: oneshot::Sender<Result<serde_json::Value, Error>>,
+}
+
+impl Supervisor {
+    async fn run(mut self, mut ws_stream: WsStream) {
+        loop {
+            let mut error = match self.run_session(ws_stream).await {
+                Ok(()) => return,
+                Err(e) => e,
+            };
+            for (_, request) in std::mem::take(&mut self.pending) {
+                match &request.message {
+                    PhoenixMessage::Subscribe(collection)
+                        if self.is_subscribed(&collection.to_string()) =>
+                    {
+                        self.rejoining.push(request)
+                    }
+                    // the left topics are not joined again, so the leave is complete
+                    PhoenixMessage::Unsubscribe(_) => {
+                        let _ = request.reply_tx.send(Ok(serde_json::Value::Null));
+                    }
+                    _ => {
+                        let _ = request.reply_tx.send(Err(Error::SocketClosed));
+                    }
+                }
+            }
+            let _ = self.events_tx.send(ConnectionEvent::Disconnected {
+                reason: error.to_string(),
+            });
+
+            let mut attempts = 0;
+            ws_stream = loop {
+                // nobody is reading anymore, so there is no point in reconnecting
+                if self.read_tx.is_closed() {
+                    return;
+                }
+                if error.is_fatal()
+                    || self
+                        .reconnect
+                        .max_attempts
+                        .is_some_and(|max| attempts >= max)
+                {
+                    for request in self.rejoining.drain(..) {
+                        let _ = request.reply_tx.send(Err(Error::SocketClosed));
+                    }
+                    let _ = self.read_tx.send(Err(error)).await;
+                    return;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(self.reconnect.delay(attempts)) => {}
+                    _ = self.read_tx.closed() => return,
+                }
+                attempts += 1;
+                match connect(&self.url, self.connect_timeout).await {
+                    Ok(ws_stream) => break ws_stream,
+                    Err(e) => error = e,
+                }
+            };
+            let _ = self
+                .events_tx
+                .send(ConnectionEvent::Reconnected { attempts });
+        }
+    }
+
+    /// Handles a single connection until it fails. Returns `Ok(())` once the client is dropped.
+    async fn run_session(&mut self, ws_stream: WsStream) -> Result<(), Error> {
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+
+        for i in 0..self.subscriptions.len() {
+            let message = PhoenixMessage::Subscribe(self.subscriptions[i].collection.clone());
+            let reference = self.next_ref();
+            ws_write
+                .send(Message::binary(message.encode(reference)))
+                .await
+                .map_err(Error::from_websocket)?;
+
+            if let Some(pos) = self.rejoining.iter().position(|r| r.message == message) {
+                let request = self.rejoining.swap_remove(pos);
+                self.pending.insert(reference, request);
+            }
+        }
 
-        let result: Option<StreamEvent> = match response.payload {
-            Some(Payload::Custom(c)) => Some(c),
-            _ => None,
+        let mut heartbeat = tokio::time::interval(self.heartbeat_interval);
+        // ticks missed during a stall must not fire back-to-back before the replies are read
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self.missed_heartbeats = 0;
+        self.heartbeat_sent = None;
+        // A message waiting for room in the read buffer. Reading from the websocket pauses
+        // meanwhile, but requests and heartbeats are still sent.
+        let mut stalled: Option<Result<Received, Error>> = None;
+        let read_tx = self.read_tx.clone();
+
+        loop {
+            tokio::select! {
+                request = self.send_rx.recv() => {
+                    let request = match request {
+                        Some(request) => request,
+                        None => return Ok(()),
+                    };
+                    match &request.message {
+                        PhoenixMessage::Subscribe(collection) => {
+                            let topic = collection.to_string();
+                            match self.subscriptions.iter_mut().find(|s| s.topic == topic) {
+                                Some(subscription) => subscription.events = request.events.clone(),
+                                None => self.subscriptions.push(Subscription {
+                                    topic,
+                                    collection: collection.clone(),
+                                    events: request.events.clone(),
+                                }),
+                            }
+                        }
+                        PhoenixMessage::Unsubscribe(collection) => {
+                            let topic = collection.to_string();
+                            self.subscriptions.retain(|s| s.topic != topic);
+                        }
+                        PhoenixMessage::Heartbeat => {}
+                    }
+                    self.share_topics();
+                    let reference = self.next_ref();
+                    let frame = Message::binary(request.message.encode(reference));
+                    self.pending.insert(reference, request);
+                    ws_write.send(frame).await.map_err(Error::from_websocket)?;
+                }
+                now = heartbeat.tick() => {
+                    // replies cannot be read while the read buffer is full, so they are not
+                    // counted as missed meanwhile
+                    let overdue = self.heartbeat_sent.is_some_and(|sent| {
+                        now.duration_since(sent) >= self.heartbeat_interval
+                    });
+                    if overdue && stalled.is_none() {
+                        self.missed_heartbeats += 1;
+                        if self.max_missed_heartbeats > 0
+                            && self.missed_heartbeats >= self.max_missed_heartbeats
+                        {
+                            return Err(Error::HeartbeatTimeout);
+                        }
+                    }
+                    self.heartbeat_sent = Some(now);
+                    let reference = self.next_ref();
+                    ws_write
+                        .send(Message::binary(PhoenixMessage::Heartbeat.encode(reference)))
+                        .await
+                        .map_err(Error::from_websocket)?;
+                }
+                permit = read_tx.reserve(), if stalled.is_some() => match permit {
+                    Ok(permit) => permit.send(stalled.take().expect("stalled message")),
+                    Err(_) => return Ok(()),
+                },
+                message = ws_read.next(), if stalled.is_none() => {
+                    let payload = match message {
+                        Some(Ok(Message::Text(payload))) => match self.handle_text(payload) {
+                            Some(payload) => Ok(payload),
+                            None => continue,
+                        },
+                        Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => continue,
+                        Some(Ok(Message::Binary(_))) => {
+                            Err(Error::Protocol("unexpected binary message".into()))
+                        }
+                        Some(Ok(Message::Close(_))) | None => return Err(Error::SocketClosed),
+                        Some(Err(e)) => return Err(Error::from_websocket(e)),
+                    };
+                    match read_tx.try_send(payload) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(payload)) => stalled = Some(payload),
+                        Err(TrySendError::Closed(_)) => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_ref(&mut self) -> u64 {
+        self.last_ref += 1;
+        self.last_ref
+    }
+
+    fn share_topics(&self) {
+        let mut topics = self.topics.write().unwrap_or_else(PoisonError::into_inner);
+        *topics = self.subscriptions.iter().map(|s| s.topic.clone()).collect();
+    }
+
+    fn is_subscribed(&self, topic: &str) -> bool {
+        self.subscriptions.iter().any(|s| s.topic == topic)
+    }
+
+    /// Resolves replies the client waits for and drops events of topics which have been left
+    /// or whose type is filtered out. Returns the message if it should be passed on to the client.
+    fn handle_text(&mut self, text: String) -> Option<Received> {
+        // undecodable messages are passed on, so that the client reports them
+        let envelope = match serde_json::from_str::<Envelope>(&text) {
+            Ok(envelope) => envelope,
+            Err(_) => return Some(Received { topic: None, text }),
         };
 
-        // println!("{:#?}", result);
-        result
+        if envelope.event == "phx_reply" && envelope.topic == "phoenix" {
+            self.missed_heartbeats = 0;
+            self.heartbeat_sent = None;
+            return None;
+        }
+
+        if envelope.event == "phx_reply" {
+            let request = envelope
+                .reference
+                .and_then(|r| r.as_u64())
+                .and_then(|r| self.pending.remove(&r));
+            if let Some(request) = request {
+                let result = match serde_json::from_str::<PhoenixResponse>(&text) {
+                    Ok(PhoenixResponse {
+                        payload: Some(Payload::PushReply { status, response }),
+                        ..
+                    }) => {
+                        if status == "ok" {
+                            Ok(response)
+                        } else {
+                            Err(Error::Rejected {
+                                topic: envelope.topic.clone(),
+                                response,
+                            })
+                        }
+                    }
+                    Ok(_) => Err(Error::Protocol("reply without status".into())),
+                    Err(error) => Err(Error::Decode {
+                        raw: text.clone(),
+                        error,
+                    }),
+                };
+                // a rejected join must not be replayed on reconnect
+                if result.is_err() && matches!(request.message, PhoenixMessage::Subscribe(_)) {
+                    self.subscriptions.retain(|s| s.topic != envelope.topic);
+                    self.share_topics();
+                }
+                let _ = request.reply_tx.send(result);
+                return None;
+            }
+        }
+
+        if envelope.topic == "phoenix" {
+            return Some(Received { topic: None, text });
+        }
+        let subscription = self
+            .subscriptions
+            .iter()
+            .find(|s| s.topic == envelope.topic)?;
+        // control messages of the Phoenix protocol are never filtered
+        if let (Some(events), false) = (&subscription.events, envelope.event.starts_with("phx_")) {
+            if !envelope
+                .event
+                .parse()
+                .is_ok_and(|event| events.contains(&event))
+            {
+                return None;
+            }
+        }
+
+        Some(Received {
+            topic: Some(envelope.topic),
+            text,
+        })
     }
 }
 
+/// A message passed from the [`Supervisor`] to the [`Client`].
+struct Received {
+    /// Joined topic the message belongs to, if any.
+    topic: Option<String>,
+    text: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum PhoenixMessage {
     Heartbeat,
     Subscribe(Collection),
+    Unsubscribe(Collection),
+}
+
+impl PhoenixMessage {
+    /// Encodes the message for the websocket. Replies of the server carry the same `reference`.
+    fn encode(&self, reference: u64) -> String {
+        match self {
+            PhoenixMessage::Heartbeat => format!(
+                "{{\"topic\": \"phoenix\", \"event\": \"heartbeat\", \"payload\": {{}}, \"ref\": \"{}\"}}",
+                reference
+            ),
+            PhoenixMessage::Subscribe(collection) => format!(
+                "{{\"topic\": \"{}\", \"event\": \"phx_join\", \"payload\": {{}}, \"ref\": \"{}\"}}",
+                collection, reference
+            ),
+            PhoenixMessage::Unsubscribe(collection) => format!(
+                "{{\"topic\": \"{}\", \"event\": \"phx_leave\", \"payload\": {{}}, \"ref\": \"{}\"}}",
+                collection, reference
+            ),
+        }
+    }
+}
+
+/// Routing information of a received message, without decoding the payload.
+#[derive(Deserialize)]
+struct Envelope {
+    topic: String,
+    event: String,
+    #[serde(rename = "ref")]
+    reference: Option<Ref>,
+}
+
+/// Ref of a message, which the server echoes either as string or as number.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Ref {
+    Number(u64),
+    String(String),
+}
+
+impl Ref {
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Ref::Number(n) => Some(*n),
+            Ref::String(s) => s.parse().ok(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -119,19 +1029,3 @@ pub enum Payload<R> {
     /// A custom payload.
     Custom(R),
 }
-
-impl Display for PhoenixMessage {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PhoenixMessage::Heartbeat => write!(
-                f,
-                "{{\"topic\": \"phoenix\", \"event\": \"heartbeat\", \"payload\": {{}}, \"ref\": 0}}"
-            ),
-            PhoenixMessage::Subscribe(collection) => write!(
-                f,
-                "{{\"topic\": \"{}\", \"event\": \"phx_join\", \"payload\": {{}}, \"ref\": 0}}",
-                collection
-            ),
-        }
-    }
-}
@@ -0,0 +1,309 @@
+//! Breakdown of the payments of a Seaport order into seller proceeds, marketplace fees and royalties.
+use crate::schema::{
+    CollectionOfferData, ItemListedData, ItemType, Parameters, ProtocolData, TraitOfferData,
+};
+use ethers_core::types::{H160, U256};
+use std::collections::HashSet;
+
+/// Fee recipient of OpenSea.
+pub const OPENSEA_FEE_RECIPIENT: H160 = H160([
+    0x00, 0x00, 0xa2, 0x6b, 0x00, 0xc1, 0xf0, 0xdf, 0x00, 0x30, 0x00, 0x39, 0x00, 0x27, 0x14, 0x00,
+    0x00, 0xfa, 0xa7, 0x19,
+]);
+
+/// Known recipients used to classify the payments of an order.
+#[derive(Debug, Clone)]
+pub struct FeeRecipients {
+    marketplace: HashSet<H160>,
+    royalty: Option<HashSet<H160>>,
+}
+
+impl Default for FeeRecipients {
+    /// OpenSea as the only marketplace. Every other recipient except the seller is treated as royalty.
+    fn default() -> Self {
+        Self {
+            marketplace: HashSet::from([OPENSEA_FEE_RECIPIENT]),
+            royalty: None,
+        }
+    }
+}
+
+impl FeeRecipients {
+    /// Adds a recipient of marketplace fees.
+    pub fn marketplace(mut self, recipient: H160) -> Self {
+        self.marketplace.insert(recipient);
+        self
+    }
+
+    /// Adds a known royalty recipient.
+    ///
+    /// Once any royalty recipient is set, payments to unknown recipients are classified as
+    /// [`FeeKind::Other`] instead of [`FeeKind::Royalty`].
+    pub fn royalty(mut self, recipient: H160) -> Self {
+        self.royalty
+            .get_or_insert_with(HashSet::new)
+            .insert(recipient);
+        self
+    }
+
+    fn classify(&self, recipient: H160) -> FeeKind {
+        if self.marketplace.contains(&recipient) {
+            FeeKind::Marketplace
+        } else {
+            match &self.royalty {
+                Some(royalty) if !royalty.contains(&recipient) => FeeKind::Other,
+                _ => FeeKind::Royalty,
+            }
+        }
+    }
+}
+
+/// Whether an order sells items or offers to buy them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderSide {
+    /// The offerer sells items for a payment.
+    Listing,
+    /// The offerer pays for items.
+    Offer,
+}
+
+/// Kind of a payment within an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeKind {
+    /// Proceeds of the seller.
+    Seller,
+    /// Fee of a marketplace.
+    Marketplace,
+    /// Royalty of the creator.
+    Royalty,
+    /// Payment to an unknown recipient.
+    Other,
+}
+
+/// A single payment within an order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fee {
+    /// Kind of the payment.
+    pub kind: FeeKind,
+    /// Recipient of the payment. `None` for the seller of an offer, who is only known once it's fulfilled.
+    pub recipient: Option<H160>,
+    /// Amount in the smallest unit of the payment token.
+    pub amount: U256,
+    /// Share of the total price in basis points.
+    pub basis_points: u64,
+}
+
+/// Payments of an order, classified by recipient.
+///
+/// Amounts are taken at the start of the order, which only matters for auctions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeBreakdown {
+    /// Whether the order is a listing or an offer.
+    pub side: OrderSide,
+    /// Token the payments are made in.
+    pub token: H160,
+    /// Total price paid by the buyer.
+    pub total: U256,
+    /// Individual payments.
+    pub fees: Vec<Fee>,
+}
+
+impl FeeBreakdown {
+    /// Classifies the payments of an order.
+    ///
+    /// Returns `None` if the order has no payment in native currency or ERC-20 tokens, or if the
+    /// payments are made in different tokens.
+    pub fn new(parameters: &Parameters, recipients: &FeeRecipients) -> Option<Self> {
+        let is_payment =
+            |item_type: ItemType| matches!(item_type, ItemType::Native | ItemType::Erc20);
+
+        let side = if parameters
+            .offer
+            .iter()
+            .all(|item| is_payment(item.item_type))
+        {
+            OrderSide::Offer
+        } else {
+            OrderSide::Listing
+        };
+
+        let payments = parameters
+            .consideration
+            .iter()
+            .filter(|item| is_payment(item.item_type));
+
+        let mut fees: Vec<Fee> = Vec::new();
+        let (token, total) = match side {
+            OrderSide::Listing => {
+                let token = parameters
+                    .consideration
+                    .iter()
+                    .find(|item| is_payment(item.item_type))?
+                    .token;
+                for item in payments {
+                    if item.token != token {
+                        return None;
+                    }
+                    let kind = if item.recipient == parameters.offerer {
+                        FeeKind::Seller
+                    } else {
+                        recipients.classify(item.recipient)
+                    };
+                    fees.push(Fee {
+                        kind,
+                        recipient: Some(item.recipient),
+                        amount: item.start_amount,
+                        basis_points: 0,
+                    });
+                }
+                let total = fees.iter().fold(U256::zero(), |sum, fee| sum + fee.amount);
+                (token, total)
+            }
+            OrderSide::Offer => {
+                let first = parameters.offer.first()?;
+                if parameters
+                    .offer
+                    .iter()
+                    .any(|item| item.token != first.token)
+                {
+                    return None;
+                }
+                let total = parameters
+                    .offer
+                    .iter()
+                    .fold(U256::zero(), |sum, item| sum + item.start_amount);
+                for item in payments {
+                    if item.token != first.token {
+                        return None;
+                    }
+                    fees.push(Fee {
+                        kind: recipients.classify(item.recipient),
+                        recipient: Some(item.recipient),
+                        amount: item.start_amount,
+                        basis_points: 0,
+                    });
+                }
+                let paid = fees.iter().fold(U256::zero(), |sum, fee| sum + fee.amount);
+                fees.insert(
+                    0,
+                    Fee {
+                        kind: FeeKind::Seller,
+                        recipient: None,
+                        amount: total.saturating_sub(paid),
+                        basis_points: 0,
+                    },
+                );
+                (first.token, total)
+            }
+        };
+
+        for fee in &mut fees {
+            fee.basis_points = basis_points(fee.amount, total);
+        }
+
+        Some(Self {
+            side,
+            token,
+            total,
+            fees,
+        })
+    }
+
+    /// Sum of all payments of the given kind.
+    pub fn amount(&self, kind: FeeKind) -> U256 {
+        self.fees
+            .iter()
+            .filter(|fee| fee.kind == kind)
+            .fold(U256::zero(), |sum, fee| sum + fee.amount)
+    }
+
+    /// Share of all payments of the given kind in basis points.
+    pub fn basis_points(&self, kind: FeeKind) -> u64 {
+        basis_points(self.amount(kind), self.total)
+    }
+
+    /// Net proceeds of the seller.
+    pub fn seller_proceeds(&self) -> U256 {
+        self.amount(FeeKind::Seller)
+    }
+
+    /// Sum of all marketplace fees.
+    pub fn marketplace_fee(&self) -> U256 {
+        self.amount(FeeKind::Marketplace)
+    }
+
+    /// Sum of all royalties.
+    pub fn royalty(&self) -> U256 {
+        self.amount(FeeKind::Royalty)
+    }
+}
+
+fn basis_points(amount: U256, total: U256) -> u64 {
+    if total.is_zero() {
+        return 0;
+    }
+    let bps = amount.saturating_mul(10_000.into()) / total;
+    if bps > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        bps.as_u64()
+    }
+}
+
+impl ProtocolData {
+    /// Payments of the order, classified with the [default](FeeRecipients::default) recipients.
+    pub fn fee_breakdown(&self) -> Option<FeeBreakdown> {
+        FeeBreakdown::new(&self.parameters, &FeeRecipients::default())
+    }
+}
+
+macro_rules! impl_fee_breakdown {
+    ($($data:ty),*) => {
+        $(
+            impl $data {
+                /// Payments of the order, classified with the [default](FeeRecipients::default) recipients.
+                pub fn fee_breakdown(&self) -> Option<FeeBreakdown> {
+                    self.protocol_data.fee_breakdown()
+                }
+            }
+        )*
+    };
+}
+
+impl_fee_breakdown!(ItemListedData, CollectionOfferData, TraitOfferData);
+
+#[cfg(test)]
+mod tests {
+    use super::{FeeBreakdown, FeeKind, FeeRecipients, OrderSide};
+    use crate::schema::{ItemListedData, Payload, StreamEvent};
+    use ethers_core::types::H160;
+
+    fn listing() -> ItemListedData {
+        let event: StreamEvent =
+            serde_json::from_str(include_str!("testdata/item_listed.json")).unwrap();
+        match event.payload {
+            Payload::ItemListed(data) => data,
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+    }
+
+    #[test]
+    fn listing_breakdown() {
+        let breakdown = listing().fee_breakdown().unwrap();
+        assert_eq!(breakdown.side, OrderSide::Listing);
+        assert_eq!(breakdown.token, H160::zero());
+        assert_eq!(breakdown.total, 10u64.pow(18).into());
+        assert_eq!(breakdown.basis_points(FeeKind::Seller), 9250);
+        assert_eq!(breakdown.basis_points(FeeKind::Marketplace), 250);
+        assert_eq!(breakdown.basis_points(FeeKind::Royalty), 500);
+        assert_eq!(breakdown.basis_points(FeeKind::Other), 0);
+    }
+
+    #[test]
+    fn mixed_payment_tokens() {
+        let mut listing = listing();
+        listing.protocol_data.parameters.consideration[2].token = H160::repeat_byte(0x44);
+        let parameters = &listing.protocol_data.parameters;
+        assert!(FeeBreakdown::new(parameters, &FeeRecipients::default()).is_none());
+    }
+}
@@ -1,3 +1,8 @@
 pub mod client;
+pub mod fees;
+pub mod orderbook;
+pub mod price;
 pub mod protocol;
 pub mod schema;
+pub mod seaport;
+pub mod tracker;
@@ -0,0 +1,692 @@
+//! Live order book built from stream events.
+//!
+//! [`OrderBook`] keeps track of all listings and offers announced on the stream, keyed by their
+//! `order_hash`, and removes them again once they are cancelled, sold, invalidated or expired.
+use crate::{
+    price::{Price, Priced},
+    schema::{Address, Collection, NftId, Payload, PaymentToken, StreamEvent, TraitCriteria},
+};
+use chrono::{DateTime, Utc};
+use ethers_core::types::{H256, U256, U512};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Kind of an order in the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderKind {
+    /// An item listed for sale.
+    Listing,
+    /// An offer or bid on a single item.
+    ItemOffer,
+    /// An offer on any item of a collection.
+    CollectionOffer,
+    /// An offer on any item of a collection with a given trait.
+    TraitOffer,
+}
+
+/// An open order.
+#[derive(Debug, Clone)]
+pub struct OrderEntry {
+    /// Hash id of the order.
+    pub order_hash: H256,
+    /// Kind of the order.
+    pub kind: OrderKind,
+    /// Collection the order belongs to.
+    pub collection: Collection,
+    /// Item the order is for. `None` for collection and trait offers.
+    pub nft_id: Option<NftId>,
+    /// Trait the offer is for. Only set for trait offers.
+    pub trait_criteria: Option<TraitCriteria>,
+    /// Creator of the order.
+    pub maker: Address,
+    /// Price for all items of the order. See `payment_token` for the actual value of each unit.
+    /// `None` if the event did not carry a price.
+    pub price: Option<U256>,
+    /// Token used for payment.
+    pub payment_token: PaymentToken,
+    /// Number of items still available.
+    pub quantity: u64,
+    /// Timestamp of when the order will expire. `None` if the order does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
+    /// Timestamp of the event that announced the order.
+    pub event_timestamp: DateTime<Utc>,
+}
+
+impl OrderEntry {
+    /// Whether the order is a listing, as opposed to any kind of offer.
+    pub fn is_listing(&self) -> bool {
+        self.kind == OrderKind::Listing
+    }
+
+    /// Whether the order expired at the given time.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expiration_date.is_some_and(|date| date <= now)
+    }
+
+    fn from_payload(payload: &Payload) -> Option<Self> {
+        let entry = match payload {
+            Payload::ItemListed(data) => OrderEntry {
+                order_hash: data.order_hash,
+                kind: OrderKind::Listing,
+                collection: data.collection.clone(),
+                nft_id: data.item.nft_id.clone(),
+                trait_criteria: None,
+                maker: data.maker.clone(),
+                price: data.base_price,
+                payment_token: data.payment_token.clone(),
+                // listings without a usable quantity cannot be priced per item
+                quantity: data.quantity()?,
+                expiration_date: data.expiration_date,
+                event_timestamp: data.event_timestamp,
+            },
+            Payload::ItemReceivedOffer(data) => OrderEntry {
+                order_hash: data.order_hash,
+                kind: OrderKind::ItemOffer,
+                collection: data.collection.clone(),
+                nft_id: data.item.nft_id.clone(),
+                trait_criteria: None,
+                maker: data.maker.clone(),
+                price: data.base_price,
+                payment_token: data.payment_token.clone(),
+                quantity: data.quantity,
+                expiration_date: data.expiration_date,
+                event_timestamp: data.event_timestamp,
+            },
+            Payload::ItemReceivedBid(data) => OrderEntry {
+                order_hash: data.order_hash,
+                kind: OrderKind::ItemOffer,
+                collection: data.collection.clone(),
+                nft_id: data.item.nft_id.clone(),
+                trait_criteria: None,
+                maker: data.maker.clone(),
+                price: data.base_price,
+                payment_token: data.payment_token.clone(),
+                quantity: data.quantity,
+                expiration_date: data.expiration_date,
+                event_timestamp: data.event_timestamp,
+            },
+            Payload::CollectionOffer(data) => OrderEntry {
+                order_hash: data.order_hash,
+                kind: OrderKind::CollectionOffer,
+                collection: data.collection.clone(),
+                nft_id: None,
+                trait_criteria: None,
+                maker: data.maker.clone(),
+                price: data.base_price,
+                payment_token: data.payment_token.clone(),
+                quantity: data.quantity,
+                expiration_date: data.expiration_date,
+                event_timestamp: data.event_timestamp,
+            },
+            Payload::TraitOffer(data) => OrderEntry {
+                order_hash: data.order_hash,
+                kind: OrderKind::TraitOffer,
+                collection: data.collection.clone(),
+                nft_id: None,
+                trait_criteria: Some(data.trait_criteria.clone()),
+                maker: data.maker.clone(),
+                price: data.base_price,
+                payment_token: data.payment_token.clone(),
+                quantity: data.quantity,
+                expiration_date: data.expiration_date,
+                event_timestamp: data.event_timestamp,
+            },
+            _ => return None,
+        };
+        Some(entry)
+    }
+}
+
+impl Priced for OrderEntry {
+    fn price(&self) -> Option<Price<'_>> {
+        Some(Price::new(self.price?, self.quantity, &self.payment_token))
+    }
+}
+
+/// Reason an order was removed from the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Removal {
+    /// The order was cancelled by its maker.
+    Cancelled,
+    /// All items of the order were sold.
+    Sold,
+    /// The order can no longer be fulfilled, e.g. because the item was transferred.
+    /// It is restored if the order is revalidated, and removed again with another reason once it
+    /// is cancelled, sold or expires.
+    Invalidated,
+    /// The order reached its expiration date.
+    Expired,
+}
+
+/// Change to the book caused by an event.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// An order was added or revalidated.
+    Added(OrderEntry),
+    /// Some items of an order were sold, the entry holds the remaining quantity and its price.
+    PartiallyFilled(OrderEntry),
+    /// An order was removed.
+    Removed {
+        /// The removed order.
+        order: OrderEntry,
+        /// Why the order was removed.
+        reason: Removal,
+    },
+}
+
+impl Change {
+    /// The order affected by the change.
+    pub fn order(&self) -> &OrderEntry {
+        match self {
+            Change::Added(order) | Change::PartiallyFilled(order) => order,
+            Change::Removed { order, .. } => order,
+        }
+    }
+}
+
+/// Open listings and offers, indexed per item and per collection.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    orders: HashMap<H256, OrderEntry>,
+    invalidated: HashMap<H256, OrderEntry>,
+    /// Open and invalidated orders by item.
+    by_item: HashMap<NftId, HashSet<H256>>,
+    by_collection: HashMap<String, HashSet<H256>>,
+    /// Open and invalidated orders by expiration date.
+    by_expiration: BTreeMap<DateTime<Utc>, HashSet<H256>>,
+}
+
+impl OrderBook {
+    /// Creates an empty book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the book with an event and returns the resulting changes.
+    ///
+    /// Orders which expired by the time the event was sent are removed as well.
+    pub fn apply(&mut self, event: &StreamEvent) -> Vec<Change> {
+        let mut changes = self.remove_expired(event.sent_at);
+
+        if let Some(entry) = OrderEntry::from_payload(&event.payload) {
+            if !entry.is_expired(event.sent_at) {
+                self.take_invalidated(entry.order_hash);
+                self.insert(entry.clone());
+                changes.push(Change::Added(entry));
+            }
+            return changes;
+        }
+
+        match &event.payload {
+            Payload::ItemCancelled(data) => {
+                changes.extend(self.discard(data.order_hash, Removal::Cancelled));
+            }
+            Payload::ItemSold(data) => {
+                match self.orders.get_mut(&data.order_hash) {
+                    Some(order) if order.quantity > data.quantity => {
+                        let remaining = order.quantity - data.quantity;
+                        // the price covers all items, so it shrinks with the quantity
+                        order.price = order
+                            .price
+                            .map(|price| scale(price, remaining, order.quantity));
+                        order.quantity = remaining;
+                        changes.push(Change::PartiallyFilled(order.clone()));
+                    }
+                    _ => changes.extend(self.discard(data.order_hash, Removal::Sold)),
+                }
+            }
+            Payload::OrderInvalidate(data) => {
+                let hashes = match data.order_hash {
+                    Some(hash) => vec![hash],
+                    // Without a hash, all orders on the item are affected.
+                    None => data
+                        .item
+                        .nft_id
+                        .as_ref()
+                        .and_then(|id| self.by_item.get(id))
+                        .map(|hashes| hashes.iter().copied().collect())
+                        .unwrap_or_default(),
+                };
+                for hash in hashes {
+                    if let Some(change) = self.remove(hash, Removal::Invalidated) {
+                        self.invalidate(change.order().clone());
+                        changes.push(change);
+                    }
+                }
+            }
+            Payload::OrderRevalidate(data) => {
+                if let Some(entry) = self.take_invalidated(data.order_hash) {
+                    if !entry.is_expired(event.sent_at) {
+                        self.insert(entry.clone());
+                        changes.push(Change::Added(entry));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        changes
+    }
+
+    /// Removes all orders which expired at the given time.
+    pub fn remove_expired(&mut self, now: DateTime<Utc>) -> Vec<Change> {
+        let mut changes = Vec::new();
+        while let Some(entry) = self.by_expiration.first_entry() {
+            if *entry.key() > now {
+                break;
+            }
+            for hash in entry.remove() {
+                changes.extend(self.discard(hash, Removal::Expired));
+            }
+        }
+        changes
+    }
+
+    /// Earliest expiration date of the open orders.
+    ///
+    /// Expired orders are only removed by the next event or [`OrderBook::remove_expired`], so a
+    /// timer for this date lets them be removed on time even if no event arrives.
+    pub fn next_expiration(&self) -> Option<DateTime<Utc>> {
+        self.by_expiration
+            .iter()
+            .find(|(_, hashes)| hashes.iter().any(|hash| self.orders.contains_key(hash)))
+            .map(|(date, _)| *date)
+    }
+
+    /// Open order with the given hash.
+    pub fn get(&self, order_hash: &H256) -> Option<&OrderEntry> {
+        self.orders.get(order_hash)
+    }
+
+    /// All open orders.
+    pub fn orders(&self) -> impl Iterator<Item = &OrderEntry> {
+        self.orders.values()
+    }
+
+    /// Open listings and offers on a single item. Collection and trait offers are not included.
+    pub fn item_orders(&self, nft_id: &NftId) -> impl Iterator<Item = &OrderEntry> {
+        self.lookup(self.by_item.get(nft_id))
+    }
+
+    /// Open orders of a collection, including collection and trait offers.
+    pub fn collection_orders(&self, slug: &str) -> impl Iterator<Item = &OrderEntry> {
+        self.lookup(self.by_collection.get(slug))
+    }
+
+    /// Open listings of an item.
+    pub fn listings(&self, nft_id: &NftId) -> impl Iterator<Item = &OrderEntry> {
+        self.item_orders(nft_id).filter(|order| order.is_listing())
+    }
+
+    /// Open offers on an item.
+    pub fn offers(&self, nft_id: &NftId) -> impl Iterator<Item = &OrderEntry> {
+        self.item_orders(nft_id).filter(|order| !order.is_listing())
+    }
+
+    /// Invalidated listings of an item, which are restored if they are revalidated.
+    pub fn invalidated_listings(&self, nft_id: &NftId) -> impl Iterator<Item = &OrderEntry> {
+        self.by_item
+            .get(nft_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|hash| self.invalidated.get(hash))
+            .filter(|order| order.is_listing())
+    }
+
+    /// Number of open orders.
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Whether there are no open orders.
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    fn lookup<'a>(
+        &'a self,
+        hashes: Option<&'a HashSet<H256>>,
+    ) -> impl Iterator<Item = &'a OrderEntry> {
+        hashes
+            .into_iter()
+            .flatten()
+            .filter_map(|hash| self.orders.get(hash))
+    }
+
+    fn insert(&mut self, entry: OrderEntry) {
+        if let Some(nft_id) = &entry.nft_id {
+            self.by_item
+                .entry(nft_id.clone())
+                .or_default()
+                .insert(entry.order_hash);
+        }
+        self.by_collection
+            .entry(entry.collection.slug().to_string())
+            .or_default()
+            .insert(entry.order_hash);
+        // a repeated event may announce a different expiration for the same order
+        if let Some(previous) = self.orders.remove(&entry.order_hash) {
+            self.unindex_expiration(&previous);
+        }
+        self.index_expiration(&entry);
+        self.orders.insert(entry.order_hash, entry);
+    }
+
+    fn remove(&mut self, order_hash: H256, reason: Removal) -> Option<Change> {
+        let order = self.orders.remove(&order_hash)?;
+
+        if let Some(nft_id) = &order.nft_id {
+            remove_index(&mut self.by_item, nft_id, &order_hash);
+        }
+        remove_index(
+            &mut self.by_collection,
+            order.collection.slug(),
+            &order_hash,
+        );
+        self.unindex_expiration(&order);
+
+        Some(Change::Removed { order, reason })
+    }
+
+    /// Removes an order for good, whether it is open or invalidated.
+    fn discard(&mut self, order_hash: H256, reason: Removal) -> Option<Change> {
+        match self.take_invalidated(order_hash) {
+            Some(order) => Some(Change::Removed { order, reason }),
+            None => self.remove(order_hash, reason),
+        }
+    }
+
+    /// Keeps an order which may be revalidated later.
+    fn invalidate(&mut self, entry: OrderEntry) {
+        if let Some(nft_id) = &entry.nft_id {
+            self.by_item
+                .entry(nft_id.clone())
+                .or_default()
+                .insert(entry.order_hash);
+        }
+        self.index_expiration(&entry);
+        self.invalidated.insert(entry.order_hash, entry);
+    }
+
+    fn take_invalidated(&mut self, order_hash: H256) -> Option<OrderEntry> {
+        let entry = self.invalidated.remove(&order_hash)?;
+        if let Some(nft_id) = &entry.nft_id {
+            remove_index(&mut self.by_item, nft_id, &order_hash);
+        }
+        self.unindex_expiration(&entry);
+        Some(entry)
+    }
+
+    fn index_expiration(&mut self, entry: &OrderEntry) {
+        if let Some(date) = entry.expiration_date {
+            self.by_expiration
+                .entry(date)
+                .or_default()
+                .insert(entry.order_hash);
+        }
+    }
+
+    fn unindex_expiration(&mut self, entry: &OrderEntry) {
+        if let Some(date) = entry.expiration_date {
+            if let Some(hashes) = self.by_expiration.get_mut(&date) {
+                hashes.remove(&entry.order_hash);
+                if hashes.is_empty() {
+                    self.by_expiration.remove(&date);
+                }
+            }
+        }
+    }
+}
+
+/// `amount * numerator / denominator`, without overflowing in between.
+fn scale(amount: U256, numerator: u64, denominator: u64) -> U256 {
+    let scaled = amount.full_mul(numerator.into()) / U512::from(denominator);
+    U256::try_from(scaled).unwrap_or(U256::MAX)
+}
+
+fn remove_index<K, Q>(index: &mut HashMap<K, HashSet<H256>>, key: &Q, order_hash: &H256)
+where
+    K: std::borrow::Borrow<Q> + std::hash::Hash + Eq,
+    Q: std::hash::Hash + Eq + ?Sized,
+{
+    if let Some(hashes) = index.get_mut(key) {
+        hashes.remove(order_hash);
+        if hashes.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, OrderBook, Removal};
+    use crate::schema::{Payload, StreamEvent};
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ethers_core::types::{H256, U256};
+    use serde_json::{json, Value};
+
+    const HASH: &str = "0x8e1375feeeee968cd1e8f33565209555df1513ada12d2cf6e8d43176990e4515";
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn eth(amount: u64) -> U256 {
+        U256::from(amount) * U256::exp10(18)
+    }
+
+    fn event(event_type: &str, sent_at: DateTime<Utc>, mut payload: Value) -> StreamEvent {
+        payload["collection"] = json!({ "slug": "pudgypenguins" });
+        payload["event_timestamp"] = json!(sent_at);
+        payload["item"] = json!({
+            "chain": { "name": "ethereum" },
+            "metadata": null,
+            "nft_id": "ethereum/0xbd3531da5cf5857e7cfaa92426877b022e612cf8/1234",
+            "permalink": null
+        });
+        serde_json::from_value(json!({
+            "event_type": event_type,
+            "sent_at": sent_at,
+            "payload": payload,
+        }))
+        .unwrap()
+    }
+
+    fn listed(
+        sent_at: DateTime<Utc>,
+        quantity: u64,
+        expiration: Option<DateTime<Utc>>,
+    ) -> StreamEvent {
+        let mut event: StreamEvent =
+            serde_json::from_str(include_str!("testdata/item_listed.json")).unwrap();
+        event.sent_at = sent_at;
+        match &mut event.payload {
+            Payload::ItemListed(data) => {
+                data.event_timestamp = sent_at;
+                data.expiration_date = expiration;
+                data.base_price = Some(eth(quantity));
+                data.protocol_data.parameters.offer[0].start_amount = quantity.into();
+            }
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+        event
+    }
+
+    fn sold(sent_at: DateTime<Utc>, quantity: u64) -> StreamEvent {
+        event(
+            "item_sold",
+            sent_at,
+            json!({
+                "closing_date": sent_at,
+                "is_private": false,
+                "listing_type": null,
+                "maker": { "address": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266" },
+                "order_hash": HASH,
+                "payment_token": {
+                    "address": "0x0000000000000000000000000000000000000000",
+                    "decimals": 18,
+                    "eth_price": "1",
+                    "name": "Ether",
+                    "symbol": "ETH",
+                    "usd_price": "3000"
+                },
+                "quantity": quantity,
+                "sale_price": eth(quantity).to_string(),
+                "taker": { "address": "0x2222222222222222222222222222222222222222" },
+                "transaction": {
+                    "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "timestamp": sent_at
+                }
+            }),
+        )
+    }
+
+    fn validation(event_type: &str, sent_at: DateTime<Utc>) -> StreamEvent {
+        event(
+            event_type,
+            sent_at,
+            json!({
+                "chain": { "name": "ethereum" },
+                "order_hash": HASH,
+                "protocol_address": "0x0000000000000068f116a894984e2db1123eb395"
+            }),
+        )
+    }
+
+    fn hash() -> H256 {
+        HASH.parse().unwrap()
+    }
+
+    #[test]
+    fn partial_sale() {
+        let mut book = OrderBook::new();
+        book.apply(&listed(at(0), 5, None));
+
+        let changes = book.apply(&sold(at(1), 2));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::PartiallyFilled(order)] if order.quantity == 3 && order.price == Some(eth(3))
+        ));
+        let order = book.get(&hash()).unwrap();
+        assert_eq!(order.quantity, 3);
+        assert_eq!(order.price, Some(eth(3)));
+
+        let changes = book.apply(&sold(at(2), 3));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Removed {
+                reason: Removal::Sold,
+                ..
+            }]
+        ));
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn invalidate_and_revalidate() {
+        let mut book = OrderBook::new();
+        book.apply(&listed(at(0), 1, Some(at(60))));
+
+        let changes = book.apply(&validation("order_invalidate", at(1)));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Removed {
+                reason: Removal::Invalidated,
+                ..
+            }]
+        ));
+        assert!(book.is_empty());
+        assert_eq!(book.next_expiration(), None);
+
+        let changes = book.apply(&validation("order_revalidate", at(2)));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Added(order)] if order.order_hash == hash()
+        ));
+        assert_eq!(book.get(&hash()).unwrap().price, Some(eth(1)));
+        assert_eq!(book.next_expiration(), Some(at(60)));
+    }
+
+    #[test]
+    fn cancel_invalidated() {
+        let mut book = OrderBook::new();
+        let listing = listed(at(0), 1, Some(at(60)));
+        let nft_id = listing.payload.nft_id().unwrap().clone();
+        book.apply(&listing);
+        book.apply(&validation("order_invalidate", at(1)));
+        assert_eq!(book.invalidated_listings(&nft_id).count(), 1);
+
+        let cancelled = event(
+            "item_cancelled",
+            at(2),
+            json!({
+                "base_price": eth(1).to_string(),
+                "is_private": false,
+                "listing_date": at(0),
+                "listing_type": null,
+                "order_hash": HASH,
+                "payment_token": {
+                    "address": "0x0000000000000000000000000000000000000000",
+                    "decimals": 18,
+                    "eth_price": "1",
+                    "name": "Ether",
+                    "symbol": "ETH",
+                    "usd_price": "3000"
+                },
+                "quantity": 1,
+                "transaction": null
+            }),
+        );
+        let changes = book.apply(&cancelled);
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Removed {
+                reason: Removal::Cancelled,
+                ..
+            }]
+        ));
+        assert_eq!(book.invalidated_listings(&nft_id).count(), 0);
+        assert!(book.by_item.is_empty());
+        assert!(book.by_expiration.is_empty());
+    }
+
+    #[test]
+    fn revalidate_after_expiry() {
+        let mut book = OrderBook::new();
+        book.apply(&listed(at(0), 1, Some(at(10))));
+        book.apply(&validation("order_invalidate", at(1)));
+
+        // the invalidated order is discarded before the revalidation is applied
+        let changes = book.apply(&validation("order_revalidate", at(20)));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Removed {
+                reason: Removal::Expired,
+                ..
+            }]
+        ));
+        assert!(book.is_empty());
+        assert!(book.by_expiration.is_empty());
+    }
+
+    #[test]
+    fn reannounced_expiration() {
+        let mut book = OrderBook::new();
+        book.apply(&listed(at(0), 1, Some(at(10))));
+        book.apply(&listed(at(1), 1, Some(at(30))));
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.next_expiration(), Some(at(30)));
+
+        assert!(book.remove_expired(at(15)).is_empty());
+        assert!(book.get(&hash()).is_some());
+
+        let changes = book.remove_expired(at(30));
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::Removed {
+                reason: Removal::Expired,
+                ..
+            }]
+        ));
+        assert!(book.by_expiration.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//! Decimal-aware prices of listings, offers and sales.
+//!
+//! Prices in events are raw `U256` amounts of the smallest unit of a [`PaymentToken`].
+//! [`Price`] combines such an amount with its token so it can be converted into token units,
+//! ETH or USD without going through `f64`.
+use crate::schema::{
+    CollectionOfferData, ItemCancelledData, ItemListedData, ItemReceivedBidData,
+    ItemReceivedOfferData, ItemSoldData, PaymentToken, TraitOfferData,
+};
+use ethers_core::types::U256;
+pub use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// A raw amount of a payment token, covering `quantity` items.
+#[derive(Debug, Clone, Copy)]
+pub struct Price<'a> {
+    /// Amount in the smallest unit of `token`.
+    pub amount: U256,
+    /// Number of items the amount pays for.
+    pub quantity: u64,
+    /// Token the amount is denominated in.
+    pub token: &'a PaymentToken,
+}
+
+impl<'a> Price<'a> {
+    /// Creates a price of `amount` for `quantity` items.
+    pub fn new(amount: U256, quantity: u64, token: &'a PaymentToken) -> Self {
+        Self {
+            amount,
+            quantity,
+            token,
+        }
+    }
+
+    /// Total amount in whole token units, e.g. `1.5` for 1.5 WETH.
+    ///
+    /// Returns `None` if the amount does not fit into a [`Decimal`].
+    pub fn decimal(&self) -> Option<Decimal> {
+        let amount = i128::try_from(u128::try_from(self.amount).ok()?).ok()?;
+        let decimals = self.token.decimals;
+        // A decimal holds at most 28 fractional digits, divide out the rest.
+        let scale = decimals.min(Decimal::MAX_SCALE as u64);
+        let mut value = Decimal::try_from_i128_with_scale(amount, scale as u32).ok()?;
+        for _ in scale..decimals {
+            value /= Decimal::TEN;
+        }
+        Some(value.normalize())
+    }
+
+    /// Total amount denominated in ETH, using the token's `eth_price`.
+    pub fn in_eth(&self) -> Option<Decimal> {
+        self.decimal()?
+            .checked_mul(f64_to_decimal(self.token.eth_price)?)
+    }
+
+    /// Total amount denominated in USD, using the token's `usd_price`.
+    pub fn in_usd(&self) -> Option<Decimal> {
+        self.decimal()?
+            .checked_mul(f64_to_decimal(self.token.usd_price)?)
+    }
+
+    /// Amount per item in whole token units.
+    pub fn unit_decimal(&self) -> Option<Decimal> {
+        per_unit(self.decimal()?, self.quantity)
+    }
+
+    /// Amount per item denominated in ETH.
+    pub fn unit_in_eth(&self) -> Option<Decimal> {
+        per_unit(self.in_eth()?, self.quantity)
+    }
+
+    /// Amount per item denominated in USD.
+    pub fn unit_in_usd(&self) -> Option<Decimal> {
+        per_unit(self.in_usd()?, self.quantity)
+    }
+}
+
+fn per_unit(total: Decimal, quantity: u64) -> Option<Decimal> {
+    total
+        .checked_div(Decimal::from(quantity))
+        .map(|d| d.normalize())
+}
+
+/// Converts through the shortest string representation, so that a rate of `0.1` stays exactly `0.1`.
+fn f64_to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_str(&value.to_string()).ok()
+}
+
+/// Payload data that carries a price.
+///
+/// Implemented for every listing, offer and sale payload. All methods return `None` if the
+/// payload has no price, or no usable quantity.
+pub trait Priced {
+    /// Raw price together with its payment token.
+    fn price(&self) -> Option<Price<'_>>;
+
+    /// Price in whole token units.
+    fn price_decimal(&self) -> Option<Decimal> {
+        self.price()?.decimal()
+    }
+
+    /// Price denominated in ETH.
+    fn price_in_eth(&self) -> Option<Decimal> {
+        self.price()?.in_eth()
+    }
+
+    /// Price denominated in USD.
+    fn price_in_usd(&self) -> Option<Decimal> {
+        self.price()?.in_usd()
+    }
+
+    /// Price per item in whole token units.
+    fn unit_price_decimal(&self) -> Option<Decimal> {
+        self.price()?.unit_decimal()
+    }
+
+    /// Price per item denominated in ETH.
+    fn unit_price_in_eth(&self) -> Option<Decimal> {
+        self.price()?.unit_in_eth()
+    }
+
+    /// Price per item denominated in USD.
+    fn unit_price_in_usd(&self) -> Option<Decimal> {
+        self.price()?.unit_in_usd()
+    }
+}
+
+macro_rules! impl_priced {
+    ($($data:ty => $amount:ident,)*) => {
+        $(
+            impl Priced for $data {
+                fn price(&self) -> Option<Price<'_>> {
+                    Some(Price::new(self.$amount?, self.quantity, &self.payment_token))
+                }
+            }
+        )*
+    };
+}
+
+impl_priced! {
+    ItemSoldData => sale_price,
+    ItemCancelledData => base_price,
+    ItemReceivedOfferData => base_price,
+    ItemReceivedBidData => base_price,
+    CollectionOfferData => base_price,
+    TraitOfferData => base_price,
+}
+
+impl Priced for ItemListedData {
+    fn price(&self) -> Option<Price<'_>> {
+        Some(Price::new(
+            self.base_price?,
+            self.quantity()?,
+            &self.payment_token,
+        ))
+    }
+}
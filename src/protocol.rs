@@ -1,5 +1,5 @@
-use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
 
 /// A collection whose events can be subscribed to.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -26,12 +26,13 @@ impl Display for Collection {
 /// The websocket to connect to.
 ///
 /// OpenSea provides two websockets for either `Mainnet` (production) networks for `Testnet` networks.
-/// See [`Chain`](crate::schema::Chain) for a full list of supported chains.
-/// #[derive(Debug)]
+/// See [`Chain`](crate::schema::Chain) for a full list of supported chains, and
+/// [`Chain::network`](crate::schema::Chain::network) for the network each chain belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Network {
-    /// Mainnet (`Ethereum`, `Polygon`, `Klaytn`, `Solana`)
+    /// Mainnet (`Ethereum`, `Polygon`, `Klaytn`, `Solana`, ...)
     Mainnet,
-    /// Testnet (`Goerli`, `Mumbai`, `Baobab`)
+    /// Testnet (`Sepolia`, `Amoy`, `Baobab`, ...)
     Testnet,
 }
 
@@ -48,7 +49,7 @@ impl Display for Network {
 ///
 /// This type belongs to the `event` field of [`Message`](phyllo::message::Message), not to be confused with
 /// [`Payload`](crate::schema::Payload).
-#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum Event {
@@ -74,4 +75,15 @@ pub enum Event {
     OrderInvalidate,
     /// an event that revalidates an order.
     OrderRevalidate,
+    /// An event which is not known to this version of the library.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl FromStr for Event {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
 }
@@ -1,10 +1,12 @@
 use crate::protocol::Event;
 use chrono::{DateTime, Utc};
-use ethers_core::{
-    abi::Address,
-    types::{H256, U256},
+use ethers_core::types::{H160, H256, U256};
+use serde::{
+    de::{DeserializeSeed, Error, IgnoredAny, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Serialize,
 };
-use serde::{de::Error, Deserialize, Serialize};
+use serde_path_to_error::Segment;
 use serde_with::serde_as;
 use std::{fmt, str::FromStr};
 use url::Url;
@@ -17,16 +19,155 @@ pub struct StreamEvent {
     /// Contents of the message
     #[serde(flatten)]
     pub payload: Payload,
+    /// Fields which could not be decoded. Only filled by [`StreamEvent::from_str_lenient`].
+    #[serde(skip)]
+    pub warnings: Vec<DecodeWarning>,
+}
+
+impl StreamEvent {
+    /// Decodes an event, tolerating fields which cannot be decoded.
+    ///
+    /// A field which fails to decode is replaced by `None`. If the field is not optional, the
+    /// closest optional field containing it is replaced instead. If there is none, the payload is
+    /// kept undecoded as [`Payload::Unknown`], with a single warning for the field which caused it.
+    /// Every replacement is recorded in [`StreamEvent::warnings`].
+    pub fn from_str_lenient(s: &str) -> Result<Self, serde_json::Error> {
+        let mut value: serde_json::Value = serde_json::from_str(s)?;
+
+        let sent_at = match value.get("sent_at") {
+            Some(sent_at) => DateTime::<Utc>::deserialize(sent_at)?,
+            None => return Err(serde_json::Error::missing_field("sent_at")),
+        };
+        let event_type = match value.get("event_type") {
+            Some(event_type) => String::deserialize(event_type)?,
+            None => return Err(serde_json::Error::missing_field("event_type")),
+        };
+        let original = match value.get_mut("payload") {
+            Some(payload) => payload.take(),
+            None => return Err(serde_json::Error::missing_field("payload")),
+        };
+
+        let mut payload = original.clone();
+        let mut warnings = Vec::new();
+        // first failing field of the current chain of replacements, and why it failed
+        let mut origin: Option<(Vec<String>, String)> = None;
+        loop {
+            let mut track = serde_path_to_error::Track::new();
+            let deserializer = serde_path_to_error::Deserializer::new(&payload, &mut track);
+            let error = match PayloadSeed(&event_type).deserialize(deserializer) {
+                Ok(payload) => {
+                    return Ok(StreamEvent {
+                        sent_at,
+                        payload,
+                        warnings,
+                    })
+                }
+                Err(error) => error,
+            };
+
+            // only the part of the path which maps to the JSON structure can be replaced
+            let mut path: Vec<String> = track
+                .path()
+                .iter()
+                .map_while(|segment| match segment {
+                    Segment::Map { key } => Some(key.clone()),
+                    Segment::Seq { index } => Some(index.to_string()),
+                    _ => None,
+                })
+                .collect();
+            let field = path.clone();
+
+            // replace the failing field, or the closest containing field which is not null yet
+            let pointer = loop {
+                match payload.pointer_mut(&json_pointer(&path)) {
+                    Some(value) if !value.is_null() => {
+                        value.take();
+                        break Some(json_pointer(&path));
+                    }
+                    _ if path.pop().is_some() => {}
+                    _ => break None,
+                }
+            };
+
+            // replacing a field supersedes the warnings of the fields it contains
+            let path = warning_path(&path);
+            let mut message = error.to_string();
+            let mut superseded = false;
+            warnings.retain(|w: &DecodeWarning| {
+                let contained = w.path == path || w.path.starts_with(&format!("{}.", path));
+                if contained {
+                    message = w.message.clone();
+                    superseded = true;
+                }
+                !contained
+            });
+            if !superseded {
+                origin = Some((field, message.clone()));
+            }
+
+            match pointer {
+                Some(pointer) => warnings.push(DecodeWarning {
+                    path,
+                    message,
+                    raw: original.pointer(&pointer).cloned().unwrap_or_default(),
+                }),
+                None => {
+                    // report the field which caused the payload to be dropped
+                    let (field, message) = origin.unwrap_or_default();
+                    return Ok(StreamEvent {
+                        sent_at,
+                        warnings: vec![DecodeWarning {
+                            path: warning_path(&field),
+                            message,
+                            raw: original
+                                .pointer(&json_pointer(&field))
+                                .cloned()
+                                .unwrap_or_default(),
+                        }],
+                        payload: Payload::Unknown {
+                            event_type,
+                            payload: original,
+                        },
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn json_pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|p| format!("/{}", p.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn warning_path(path: &[String]) -> String {
+    if path.is_empty() {
+        "payload".to_string()
+    } else {
+        format!("payload.{}", path.join("."))
+    }
+}
+
+/// A field which could not be decoded by [`StreamEvent::from_str_lenient`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeWarning {
+    /// Path of the replaced field, e.g. `payload.item.chain`.
+    pub path: String,
+    /// Reason why decoding failed.
+    pub message: String,
+    /// Original value of the replaced field. If the whole payload was kept as
+    /// [`Payload::Unknown`], this is the value of the field which failed to decode.
+    pub raw: serde_json::Value,
 }
 
 /// Content of the message.
 ///
 /// This type corresponds to the JSON objects recieved [as described here](https://docs.opensea.io/reference/stream-api-event-schemas),
 /// not the event type used for the Phoenix protocol (see [`Event`]).
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "event_type", content = "payload")]
-#[serde(rename_all = "snake_case")]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
 pub enum Payload {
     /// An item has been listed for sale.
     ItemListed(ItemListedData),
@@ -50,6 +191,262 @@ pub enum Payload {
     OrderInvalidate(OrderInvalidateData),
     /// An order has been revalidated.
     OrderRevalidate(OrderRevalidateData),
+    /// An event type which is not known to this version of the library.
+    /// The payload is kept as is.
+    Unknown {
+        /// Type of the event.
+        event_type: String,
+        /// Undecoded payload.
+        payload: serde_json::Value,
+    },
+}
+
+impl Payload {
+    fn event_type(&self) -> &str {
+        match self {
+            Payload::ItemListed(_) => "item_listed",
+            Payload::ItemSold(_) => "item_sold",
+            Payload::ItemTransferred(_) => "item_transferred",
+            Payload::ItemMetadataUpdated(_) => "item_metadata_updated",
+            Payload::ItemCancelled(_) => "item_cancelled",
+            Payload::ItemReceivedOffer(_) => "item_received_offer",
+            Payload::ItemReceivedBid(_) => "item_received_bid",
+            Payload::CollectionOffer(_) => "collection_offer",
+            Payload::TraitOffer(_) => "trait_offer",
+            Payload::OrderInvalidate(_) => "order_invalidate",
+            Payload::OrderRevalidate(_) => "order_revalidate",
+            Payload::Unknown { event_type, .. } => event_type,
+        }
+    }
+
+    /// Collection the event belongs to.
+    pub fn collection(&self) -> Option<&Collection> {
+        match self {
+            Payload::ItemListed(data) => Some(&data.collection),
+            Payload::ItemSold(data) => Some(&data.collection),
+            Payload::ItemTransferred(data) => Some(&data.collection),
+            Payload::ItemMetadataUpdated(data) => Some(&data.collection),
+            Payload::ItemCancelled(data) => Some(&data.collection),
+            Payload::ItemReceivedOffer(data) => Some(&data.collection),
+            Payload::ItemReceivedBid(data) => Some(&data.collection),
+            Payload::CollectionOffer(data) => Some(&data.collection),
+            Payload::TraitOffer(data) => Some(&data.collection),
+            Payload::OrderInvalidate(data) => Some(&data.collection),
+            Payload::OrderRevalidate(data) => Some(&data.collection),
+            Payload::Unknown { .. } => None,
+        }
+    }
+
+    /// Item the event is about. `None` for collection and trait offers.
+    pub fn item(&self) -> Option<&Item> {
+        match self {
+            Payload::ItemListed(data) => Some(&data.item),
+            Payload::ItemSold(data) => Some(&data.item),
+            Payload::ItemTransferred(data) => Some(&data.item),
+            Payload::ItemMetadataUpdated(data) => Some(&data.item),
+            Payload::ItemCancelled(data) => Some(&data.item),
+            Payload::ItemReceivedOffer(data) => Some(&data.item),
+            Payload::ItemReceivedBid(data) => Some(&data.item),
+            Payload::OrderInvalidate(data) => Some(&data.item),
+            Payload::OrderRevalidate(data) => Some(&data.item),
+            Payload::CollectionOffer(_) | Payload::TraitOffer(_) | Payload::Unknown { .. } => None,
+        }
+    }
+
+    /// Identifier of the item the event is about.
+    pub fn nft_id(&self) -> Option<&NftId> {
+        self.item()?.nft_id.as_ref()
+    }
+
+    /// Timestamp of the event.
+    pub fn event_timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Payload::ItemListed(data) => Some(data.event_timestamp),
+            Payload::ItemSold(data) => Some(data.event_timestamp),
+            Payload::ItemTransferred(data) => Some(data.event_timestamp),
+            Payload::ItemCancelled(data) => Some(data.event_timestamp),
+            Payload::ItemReceivedOffer(data) => Some(data.event_timestamp),
+            Payload::ItemReceivedBid(data) => Some(data.event_timestamp),
+            Payload::CollectionOffer(data) => Some(data.event_timestamp),
+            Payload::TraitOffer(data) => Some(data.event_timestamp),
+            Payload::OrderInvalidate(data) => Some(data.event_timestamp),
+            Payload::OrderRevalidate(data) => Some(data.event_timestamp),
+            Payload::ItemMetadataUpdated(_) | Payload::Unknown { .. } => None,
+        }
+    }
+
+    /// Hash of the order the event is about.
+    pub fn order_hash(&self) -> Option<H256> {
+        match self {
+            Payload::ItemListed(data) => Some(data.order_hash),
+            Payload::ItemSold(data) => Some(data.order_hash),
+            Payload::ItemCancelled(data) => Some(data.order_hash),
+            Payload::ItemReceivedOffer(data) => Some(data.order_hash),
+            Payload::ItemReceivedBid(data) => Some(data.order_hash),
+            Payload::CollectionOffer(data) => Some(data.order_hash),
+            Payload::TraitOffer(data) => Some(data.order_hash),
+            Payload::OrderInvalidate(data) => data.order_hash,
+            Payload::OrderRevalidate(data) => Some(data.order_hash),
+            Payload::ItemTransferred(_)
+            | Payload::ItemMetadataUpdated(_)
+            | Payload::Unknown { .. } => None,
+        }
+    }
+
+    /// Creator of the order the event is about.
+    pub fn maker(&self) -> Option<&Address> {
+        match self {
+            Payload::ItemListed(data) => Some(&data.maker),
+            Payload::ItemSold(data) => Some(&data.maker),
+            Payload::ItemReceivedOffer(data) => Some(&data.maker),
+            Payload::ItemReceivedBid(data) => Some(&data.maker),
+            Payload::CollectionOffer(data) => Some(&data.maker),
+            Payload::TraitOffer(data) => Some(&data.maker),
+            _ => None,
+        }
+    }
+
+    /// Taker of the order the event is about.
+    pub fn taker(&self) -> Option<&Address> {
+        match self {
+            Payload::ItemSold(data) => Some(&data.taker),
+            Payload::ItemReceivedOffer(data) => data.taker.as_ref(),
+            Payload::ItemReceivedBid(data) => data.taker.as_ref(),
+            Payload::CollectionOffer(data) => data.taker.as_ref(),
+            Payload::TraitOffer(data) => data.taker.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Chain the event happened on, taken from the item if the payload has no chain of its own.
+    pub fn chain(&self) -> Option<&Chain> {
+        match self {
+            Payload::OrderInvalidate(data) => Some(&data.chain),
+            Payload::OrderRevalidate(data) => Some(&data.chain),
+            _ => {
+                let item = self.item()?;
+                item.chain
+                    .as_ref()
+                    .or_else(|| item.nft_id.as_ref().map(|id| &id.network))
+            }
+        }
+    }
+}
+
+impl Serialize for Payload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("event_type", self.event_type())?;
+        match self {
+            Payload::ItemListed(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemSold(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemTransferred(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemMetadataUpdated(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemCancelled(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemReceivedOffer(data) => map.serialize_entry("payload", data)?,
+            Payload::ItemReceivedBid(data) => map.serialize_entry("payload", data)?,
+            Payload::CollectionOffer(data) => map.serialize_entry("payload", data)?,
+            Payload::TraitOffer(data) => map.serialize_entry("payload", data)?,
+            Payload::OrderInvalidate(data) => map.serialize_entry("payload", data)?,
+            Payload::OrderRevalidate(data) => map.serialize_entry("payload", data)?,
+            Payload::Unknown { payload, .. } => map.serialize_entry("payload", payload)?,
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(field_identifier, rename_all = "snake_case")]
+        enum Field {
+            EventType,
+            Payload,
+            #[serde(other)]
+            Other,
+        }
+
+        struct PayloadVisitor;
+
+        impl<'de> Visitor<'de> for PayloadVisitor {
+            type Value = Payload;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object with event_type and payload")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut event_type: Option<String> = None;
+                let mut payload = None;
+                // the payload has to be buffered if it comes before the event type
+                let mut buffered: Option<serde_json::Value> = None;
+
+                while let Some(field) = map.next_key()? {
+                    match (field, &event_type) {
+                        (Field::EventType, _) => event_type = Some(map.next_value()?),
+                        (Field::Payload, Some(event_type)) => {
+                            payload = Some(map.next_value_seed(PayloadSeed(event_type))?)
+                        }
+                        (Field::Payload, None) => buffered = Some(map.next_value()?),
+                        (Field::Other, _) => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                let event_type = event_type.ok_or_else(|| A::Error::missing_field("event_type"))?;
+                match (payload, buffered) {
+                    (Some(payload), _) => Ok(payload),
+                    (None, Some(buffered)) => PayloadSeed(&event_type)
+                        .deserialize(buffered)
+                        .map_err(A::Error::custom),
+                    (None, None) => Err(A::Error::missing_field("payload")),
+                }
+            }
+        }
+
+        deserializer.deserialize_map(PayloadVisitor)
+    }
+}
+
+/// Deserializes the payload of the given event type.
+struct PayloadSeed<'a>(&'a str);
+
+impl<'de, 'a> DeserializeSeed<'de> for PayloadSeed<'a> {
+    type Value = Payload;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let d = deserializer;
+        Ok(match self.0 {
+            "item_listed" => Payload::ItemListed(Deserialize::deserialize(d)?),
+            "item_sold" => Payload::ItemSold(Deserialize::deserialize(d)?),
+            "item_transferred" => Payload::ItemTransferred(Deserialize::deserialize(d)?),
+            "item_metadata_updated" => Payload::ItemMetadataUpdated(Deserialize::deserialize(d)?),
+            "item_cancelled" => Payload::ItemCancelled(Deserialize::deserialize(d)?),
+            "item_received_offer" => Payload::ItemReceivedOffer(Deserialize::deserialize(d)?),
+            "item_received_bid" => Payload::ItemReceivedBid(Deserialize::deserialize(d)?),
+            "collection_offer" => Payload::CollectionOffer(Deserialize::deserialize(d)?),
+            "trait_offer" => Payload::TraitOffer(Deserialize::deserialize(d)?),
+            "order_invalidate" => Payload::OrderInvalidate(Deserialize::deserialize(d)?),
+            "order_revalidate" => Payload::OrderRevalidate(Deserialize::deserialize(d)?),
+            event_type => Payload::Unknown {
+                event_type: event_type.to_string(),
+                payload: Deserialize::deserialize(d)?,
+            },
+        })
+    }
 }
 
 impl From<Payload> for Event {
@@ -66,14 +463,22 @@ impl From<Payload> for Event {
             Payload::TraitOffer(_) => Event::TraitOffer,
             Payload::OrderInvalidate(_) => Event::OrderInvalidate,
             Payload::OrderRevalidate(_) => Event::OrderRevalidate,
+            Payload::Unknown { event_type, .. } => Event::Other(event_type),
         }
     }
 }
 
 /// A collection on OpenSea.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Collection(String);
 
+impl Collection {
+    /// Slug of the collection.
+    pub fn slug(&self) -> &str {
+        &self.0
+    }
+}
+
 impl Serialize for Collection {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -119,11 +524,11 @@ pub struct Item {
 }
 
 /// Identifier of the NFT.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NftId {
     /// Chain the item is on.
     pub network: Chain,
-    /// Contract address.
+    /// Contract address, or the mint address for Solana items.
     pub address: Address,
     /// Token ID.
     pub id: String,
@@ -134,7 +539,7 @@ impl Serialize for NftId {
     where
         S: serde::Serializer,
     {
-        format!("{}/{:?}/{}", self.network, self.address, self.id).serialize(serializer)
+        format!("{}/{}/{}", self.network, self.address, self.id).serialize(serializer)
     }
 }
 
@@ -148,9 +553,8 @@ impl<'de> Deserialize<'de> for NftId {
 
         let network = parts
             .next()
-            .map(Chain::from_str)
-            .ok_or_else(|| D::Error::custom("expected network"))?
-            .map_err(|_| D::Error::custom("invalid network"))?;
+            .map(Chain::from)
+            .ok_or_else(|| D::Error::custom("expected network"))?;
 
         let address = parts
             .next()
@@ -158,7 +562,7 @@ impl<'de> Deserialize<'de> for NftId {
             .ok_or_else(|| D::Error::custom("expected address"))?
             .map_err(D::Error::custom)?;
 
-        let id = parts.next().map(String::from).unwrap();
+        let id = parts.next().map(String::from).unwrap_or_default();
 
         Ok(NftId {
             network,
@@ -168,96 +572,367 @@ impl<'de> Deserialize<'de> for NftId {
     }
 }
 
+/// Address of an account or contract, depending on the chain it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Address {
+    /// An address on Ethereum or another EVM chain.
+    Ethereum(H160),
+    /// A base58 encoded address on Solana.
+    Solana(String),
+}
+
+impl Address {
+    /// The address if it belongs to an EVM chain.
+    pub fn as_ethereum(&self) -> Option<H160> {
+        match self {
+            Address::Ethereum(address) => Some(*address),
+            Address::Solana(_) => None,
+        }
+    }
+
+    /// The address if it belongs to Solana.
+    pub fn as_solana(&self) -> Option<&str> {
+        match self {
+            Address::Ethereum(_) => None,
+            Address::Solana(address) => Some(address),
+        }
+    }
+}
+
+impl From<H160> for Address {
+    fn from(address: H160) -> Self {
+        Address::Ethereum(address)
+    }
+}
+
+impl PartialEq<H160> for Address {
+    fn eq(&self, other: &H160) -> bool {
+        self.as_ethereum() == Some(*other)
+    }
+}
+
+/// Error returned when parsing an [`Address`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAddressError(String);
+
+impl fmt::Display for ParseAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid address: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseAddressError {}
+
+impl FromStr for Address {
+    type Err = ParseAddressError;
+
+    /// Parses `0x` prefixed hex strings as EVM addresses and base58 strings as Solana addresses.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+        if s.starts_with("0x") {
+            H160::from_str(s)
+                .map(Address::Ethereum)
+                .map_err(|_| ParseAddressError(s.to_string()))
+        } else if (32..=44).contains(&s.len()) && s.chars().all(|c| BASE58.contains(c)) {
+            Ok(Address::Solana(s.to_string()))
+        } else {
+            Err(ParseAddressError(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Ethereum(address) => write!(f, "{:?}", address),
+            Address::Solana(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Address::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
 mod chain {
     #![allow(deprecated)]
+    use crate::protocol::Network;
     use serde::{Deserialize, Serialize};
+    use std::{convert::Infallible, fmt, str::FromStr};
+
+    /// Defines [`Chain`] and its string identifiers from a single table, so that
+    /// `FromStr`, `Display` and serde always agree.
+    macro_rules! chains {
+        ($($(#[$meta:meta])* $variant:ident => $name:literal,)*) => {
+            /// Network an item is on.
+            #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+            #[non_exhaustive]
+            pub enum Chain {
+                $($(#[$meta])* $variant,)*
+                /// A chain not known to this version of the crate, identified by its name.
+                Other(String),
+            }
+
+            impl Chain {
+                /// Every chain known to this version of the crate.
+                pub const ALL: &'static [Chain] = &[$(Chain::$variant,)*];
+
+                /// Identifier used for the chain by OpenSea.
+                pub fn as_str(&self) -> &str {
+                    match self {
+                        $(Chain::$variant => $name,)*
+                        Chain::Other(name) => name,
+                    }
+                }
+            }
 
-    /// Network an item is on.
-    #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
-    #[serde(tag = "name", rename_all = "lowercase")]
-    #[non_exhaustive]
-    pub enum Chain {
+            impl From<&str> for Chain {
+                fn from(s: &str) -> Self {
+                    match s {
+                        $($name => Chain::$variant,)*
+                        other => Chain::Other(other.to_string()),
+                    }
+                }
+            }
+        };
+    }
+
+    chains! {
         /// [Avalanche](https://www.avalabs.org/) mainnet.
-        Avalanche,
-        /// [Base] (https://base.org/) mainnet
-        Base,
+        Avalanche => "avalanche",
+        /// [Base](https://base.org/) mainnet.
+        Base => "base",
         /// [BSC](https://www.bnbchain.org/en) mainnet.
-        Bsc,
+        Bsc => "bsc",
         /// [Ethereum](https://ethereum.org) mainnet.
-        Ethereum,
-        /// Optimism
-        Optimism,
-        /// Arbitrum
-        Arbitrum,
-        // Arbitrum Nova
-        #[serde(rename = "arbitrum_nova")]
-        ArbitrumNova,
+        Ethereum => "ethereum",
+        /// [Optimism](https://www.optimism.io/) mainnet.
+        Optimism => "optimism",
+        /// [Arbitrum](https://arbitrum.io/) mainnet.
+        Arbitrum => "arbitrum",
+        /// [Arbitrum Nova](https://nova.arbitrum.io/) mainnet.
+        ArbitrumNova => "arbitrum_nova",
         /// [Polygon](https://polygon.technology/solutions/polygon-pos) mainnet.
-        #[serde(rename = "matic")]
-        Polygon,
+        Polygon => "matic",
         /// [Klaytn](https://www.klaytn.foundation/) mainnet.
-        Klaytn,
-        /// [Solana](https://solana.com/) mainnet. This variant (and all events for Solana assets) are not supported in this version.
-        Solana,
+        Klaytn => "klaytn",
+        /// [Solana](https://solana.com/) mainnet.
+        Solana => "solana",
+        /// [Zora](https://zora.co/) mainnet.
+        Zora => "zora",
+        /// [Blast](https://blast.io/) mainnet.
+        Blast => "blast",
         /// [Goerli](https://ethereum.org/en/developers/docs/networks/#goerli) testnet (of Ethereum).
-        Goerli,
+        Goerli => "goerli",
+        /// [Sepolia](https://ethereum.org/en/developers/docs/networks/#sepolia) testnet (of Ethereum).
+        Sepolia => "sepolia",
         /// [Mumbai](https://docs.polygon.technology/docs/develop/network-details/network#mumbai-pos-testnet) testnet (of Polygon).
-        Mumbai,
+        Mumbai => "mumbai",
+        /// [Amoy](https://docs.polygon.technology/tools/faucets/) testnet (of Polygon).
+        Amoy => "amoy",
         /// [Baobab](https://www.klaytn.foundation/) testnet (of Klaytn).
-        Baobab,
-        /// [Zora](https://zora.co/) mainnet.
-        Zora,
+        Baobab => "baobab",
+        /// Base Sepolia testnet (of Base).
+        BaseSepolia => "base_sepolia",
+        /// Arbitrum Sepolia testnet (of Arbitrum).
+        ArbitrumSepolia => "arbitrum_sepolia",
+        /// OP Sepolia testnet (of Optimism).
+        OptimismSepolia => "optimism_sepolia",
+        /// Zora Sepolia testnet (of Zora).
+        ZoraSepolia => "zora_sepolia",
+        /// Blast Sepolia testnet (of Blast).
+        BlastSepolia => "blast_sepolia",
     }
-}
-pub use chain::Chain;
 
-impl FromStr for Chain {
-    type Err = ();
+    /// Native currency of a chain.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct NativeCurrency {
+        /// Ticker symbol, e.g. `ETH`.
+        pub symbol: &'static str,
+        /// Number of decimals of the smallest unit.
+        pub decimals: u8,
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "avalanche" => Ok(Chain::Avalanche),
-            "base" => Ok(Chain::Base),
-            "bsc" => Ok(Chain::Bsc),
-            "ethereum" => Ok(Chain::Ethereum),
-            "optimism" => Ok(Chain::Optimism),
-            "arbitrum" => Ok(Chain::Arbitrum),
-            "arbitrum_nova" => Ok(Chain::ArbitrumNova),
-            "matic" => Ok(Chain::Polygon),
-            "klaytn" => Ok(Chain::Klaytn),
-            "solana" => Ok(Chain::Solana),
-            "mumbai" => Ok(Chain::Mumbai),
-            "baobab" => Ok(Chain::Baobab),
-            "zora" => Ok(Chain::Zora),
-            _ => Err(()),
-        }
-    }
-}
-
-impl fmt::Display for Chain {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
+    impl Chain {
+        /// Websocket network that carries events for this chain.
+        ///
+        /// Chains unknown to this version of the crate are assumed to be on [`Network::Mainnet`].
+        pub fn network(&self) -> Network {
             match self {
-                Chain::Avalanche => "avalanche",
-                Chain::Base => "base",
-                Chain::Bsc => "bsc",
-                Chain::Ethereum => "ethereum",
-                Chain::Optimism => "optimism",
-                Chain::Arbitrum => "arbitrum",
-                Chain::ArbitrumNova => "arbitrum_nova",
-                Chain::Polygon => "matic",
-                Chain::Klaytn => "klaytn",
-                Chain::Solana => "solana",
-                Chain::Mumbai => "mumbai",
-                Chain::Baobab => "baobab",
-                Chain::Goerli => "goerli",
-                Chain::Zora => "zora",
+                Chain::Avalanche
+                | Chain::Base
+                | Chain::Bsc
+                | Chain::Ethereum
+                | Chain::Optimism
+                | Chain::Arbitrum
+                | Chain::ArbitrumNova
+                | Chain::Polygon
+                | Chain::Klaytn
+                | Chain::Solana
+                | Chain::Zora
+                | Chain::Blast
+                | Chain::Other(_) => Network::Mainnet,
+                Chain::Goerli
+                | Chain::Sepolia
+                | Chain::Mumbai
+                | Chain::Amoy
+                | Chain::Baobab
+                | Chain::BaseSepolia
+                | Chain::ArbitrumSepolia
+                | Chain::OptimismSepolia
+                | Chain::ZoraSepolia
+                | Chain::BlastSepolia => Network::Testnet,
             }
-        )
+        }
+
+        /// Whether the chain is a testnet.
+        pub fn is_testnet(&self) -> bool {
+            self.network() == Network::Testnet
+        }
+
+        /// [EIP-155](https://eips.ethereum.org/EIPS/eip-155) chain id, if the chain is EVM compatible and known.
+        pub fn chain_id(&self) -> Option<u64> {
+            let id = match self {
+                Chain::Ethereum => 1,
+                Chain::Optimism => 10,
+                Chain::Bsc => 56,
+                Chain::Polygon => 137,
+                Chain::Klaytn => 8217,
+                Chain::Base => 8453,
+                Chain::Arbitrum => 42161,
+                Chain::ArbitrumNova => 42170,
+                Chain::Avalanche => 43114,
+                Chain::Blast => 81457,
+                Chain::Zora => 7777777,
+                Chain::Goerli => 5,
+                Chain::Baobab => 1001,
+                Chain::Mumbai => 80001,
+                Chain::Amoy => 80002,
+                Chain::BaseSepolia => 84532,
+                Chain::ArbitrumSepolia => 421614,
+                Chain::Sepolia => 11155111,
+                Chain::OptimismSepolia => 11155420,
+                Chain::BlastSepolia => 168587773,
+                Chain::ZoraSepolia => 999999999,
+                Chain::Solana | Chain::Other(_) => return None,
+            };
+            Some(id)
+        }
+
+        /// Currency used to pay for gas on the chain, if known.
+        pub fn native_currency(&self) -> Option<NativeCurrency> {
+            let (symbol, decimals) = match self {
+                Chain::Ethereum
+                | Chain::Optimism
+                | Chain::Arbitrum
+                | Chain::ArbitrumNova
+                | Chain::Base
+                | Chain::Zora
+                | Chain::Blast
+                | Chain::Goerli
+                | Chain::Sepolia
+                | Chain::BaseSepolia
+                | Chain::ArbitrumSepolia
+                | Chain::OptimismSepolia
+                | Chain::ZoraSepolia
+                | Chain::BlastSepolia => ("ETH", 18),
+                Chain::Polygon | Chain::Mumbai | Chain::Amoy => ("POL", 18),
+                Chain::Bsc => ("BNB", 18),
+                Chain::Avalanche => ("AVAX", 18),
+                Chain::Klaytn | Chain::Baobab => ("KLAY", 18),
+                Chain::Solana => ("SOL", 9),
+                Chain::Other(_) => return None,
+            };
+            Some(NativeCurrency { symbol, decimals })
+        }
+    }
+
+    impl FromStr for Chain {
+        type Err = Infallible;
+
+        /// Parses a chain identifier, falling back to [`Chain::Other`] for unknown chains.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(Chain::from(s))
+        }
+    }
+
+    impl fmt::Display for Chain {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    /// Chains are sent as `{"name": "ethereum"}`.
+    #[derive(Serialize, Deserialize)]
+    struct Tagged<N> {
+        name: N,
+    }
+
+    impl Serialize for Chain {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            Tagged {
+                name: self.as_str(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Chain {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            Tagged::<String>::deserialize(deserializer)
+                .map(|tagged| Chain::from(tagged.name.as_str()))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::Chain;
+        use std::collections::HashSet;
+
+        #[test]
+        fn chain_round_trip() {
+            let chains = Chain::ALL
+                .iter()
+                .cloned()
+                .chain([Chain::Other("unreleased".to_string())]);
+
+            for chain in chains {
+                let name = chain.to_string();
+                assert_eq!(name.parse::<Chain>(), Ok(chain.clone()));
+
+                let json = serde_json::to_value(&chain).unwrap();
+                assert_eq!(json, serde_json::json!({ "name": name }));
+                assert_eq!(serde_json::from_value::<Chain>(json).unwrap(), chain);
+            }
+
+            let names: HashSet<_> = Chain::ALL.iter().map(Chain::as_str).collect();
+            assert_eq!(names.len(), Chain::ALL.len());
+        }
     }
 }
+pub use chain::{Chain, NativeCurrency};
 
 /// Basic metadata of an item.
 ///
@@ -304,10 +979,10 @@ pub struct ItemListedData {
     /// Timestamp of when the listing was created.
     pub event_timestamp: DateTime<Utc>,
     /// Starting price of the listing. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
-    /// Expiration date.
-    pub expiration_date: DateTime<Utc>,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
+    /// Expiration date. `None` if the listing does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
     /// Whether the listing is private.
     pub is_private: bool,
     /// Timestamp of when the listing was created.
@@ -330,6 +1005,19 @@ pub struct ItemListedData {
     // pub taker: Option<Address>,
 }
 
+impl ItemListedData {
+    /// Number of items on sale. This is always `1` for ERC-721 tokens.
+    ///
+    /// Listings don't carry a quantity, so it is taken from the offer of the order. `None` if the
+    /// order offers no items, or an amount which is zero or does not fit into a `u64`.
+    pub fn quantity(&self) -> Option<u64> {
+        let offer = self.protocol_data.parameters.offer.first()?;
+        u64::try_from(offer.start_amount)
+            .ok()
+            .filter(|quantity| *quantity > 0)
+    }
+}
+
 /// Payload data for [`Payload::ItemSold`].
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ItemSoldData {
@@ -356,8 +1044,8 @@ pub struct ItemSoldData {
     /// Number of items bought. This is always `1` for ERC-721 tokens.
     pub quantity: u64,
     /// Purchase price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub sale_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub sale_price: Option<U256>,
     /// Buyer/winner of the listing.
     #[serde(with = "address_fromjson")]
     pub taker: Address,
@@ -400,8 +1088,8 @@ pub struct ItemMetadataUpdatedData {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ItemCancelledData {
     /// Offer price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
     //// Collection that the token belongs to.
     pub collection: Collection,
     /// Timestamp of when the listing was cancelled.
@@ -439,12 +1127,12 @@ pub struct ItemReceivedOfferData {
     /// Timestamp of when the offer was received.
     pub event_timestamp: DateTime<Utc>,
     /// Offer price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
     /// Timestamp of when the offer was created.
     pub created_date: DateTime<Utc>,
-    /// Timestamp of when the offer will expire.
-    pub expiration_date: DateTime<Utc>,
+    /// Timestamp of when the offer will expire. `None` if the offer does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
     /// Creator of the offer.
     #[serde(with = "address_fromjson")]
     pub maker: Address,
@@ -470,12 +1158,12 @@ pub struct ItemReceivedBidData {
     /// Timestamp of when the bid was received.
     pub event_timestamp: DateTime<Utc>,
     /// Bid price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
     /// Timestamp of when the bid was created.
     pub created_date: DateTime<Utc>,
-    /// Timestamp of when the bid will expire.
-    pub expiration_date: DateTime<Utc>,
+    /// Timestamp of when the bid will expire. `None` if the bid does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
     /// Creator of the bid.
     #[serde(with = "address_fromjson")]
     pub maker: Address,
@@ -497,8 +1185,8 @@ pub struct CollectionOfferData {
     #[serde(with = "address_fromjson")]
     pub asset_contract_criteria: Address,
     /// Bid price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
     //// Collection that the token belongs to.
     pub collection: Collection,
     /// Collection criteria.
@@ -507,8 +1195,8 @@ pub struct CollectionOfferData {
     pub created_date: DateTime<Utc>,
     /// Timestamp of when the bid was received.
     pub event_timestamp: DateTime<Utc>,
-    /// Timestamp of when the bid will expire.
-    pub expiration_date: DateTime<Utc>,
+    /// Timestamp of when the bid will expire. `None` if the bid does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
     /// Creator of the bid.
     #[serde(with = "address_fromjson")]
     pub maker: Address,
@@ -517,7 +1205,7 @@ pub struct CollectionOfferData {
     /// Token offered for payment.
     pub payment_token: PaymentToken,
     /// the address of the used zone
-    pub protocol_address: Address,
+    pub protocol_address: H160,
     /// the protocol data from OS
     pub protocol_data: ProtocolData,
     /// Number of items on the offer. This is always `1` for ERC-721 tokens.
@@ -534,8 +1222,8 @@ pub struct TraitOfferData {
     #[serde(with = "address_fromjson")]
     pub asset_contract_criteria: Address,
     /// Bid price. See `payment_token` for the actual value of each unit.
-    #[serde(with = "u256_fromstr_radix_10")]
-    pub base_price: U256,
+    #[serde(with = "u256_fromstr_radix_10_opt")]
+    pub base_price: Option<U256>,
     //// Collection that the token belongs to.
     pub collection: Collection,
     /// Collection criteria.
@@ -544,8 +1232,8 @@ pub struct TraitOfferData {
     pub created_date: DateTime<Utc>,
     /// Timestamp of when the bid was received.
     pub event_timestamp: DateTime<Utc>,
-    /// Timestamp of when the bid will expire.
-    pub expiration_date: DateTime<Utc>,
+    /// Timestamp of when the bid will expire. `None` if the bid does not expire.
+    pub expiration_date: Option<DateTime<Utc>>,
     /// Creator of the bid.
     #[serde(with = "address_fromjson")]
     pub maker: Address,
@@ -554,7 +1242,7 @@ pub struct TraitOfferData {
     /// Token offered for payment.
     pub payment_token: PaymentToken,
     /// the address of the used zone
-    pub protocol_address: Address,
+    pub protocol_address: H160,
     /// the protocol data from OS
     pub protocol_data: ProtocolData,
     /// Number of items on the offer. This is always `1` for ERC-721 tokens.
@@ -580,15 +1268,9 @@ pub struct OrderInvalidateData {
     /// Hash id of the listing.
     pub order_hash: Option<H256>,
     /// the address of the used zone
-    pub protocol_address: Address,
+    pub protocol_address: H160,
 }
 
-// pub enum Address {
-//     /// an ethereum address
-//     Ethereum(abi::Address),
-//     /// a solana address
-//     Solana(String),
-// }
 /// Payload data for [`Payload::OrderRevalidate`].
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OrderRevalidateData {
@@ -603,7 +1285,7 @@ pub struct OrderRevalidateData {
     /// Hash id of the listing.
     pub order_hash: H256,
     /// the address of the used zone
-    pub protocol_address: Address,
+    pub protocol_address: H160,
 }
 
 /// the criteria for the collection
@@ -613,7 +1295,7 @@ pub struct CollectionCriteria {
 }
 
 /// the criteria for the trait
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TraitCriteria {
     pub trait_name: String,
     pub trait_type: String,
@@ -685,31 +1367,51 @@ pub struct ProtocolData {
 #[serde(rename_all = "camelCase")]
 pub struct Parameters {
     /// the conduit key for this listing
-    pub conduit_key: String,
+    pub conduit_key: H256,
     /// the consideration items for the payments
     pub consideration: Vec<Consideration>,
-    /// a counter
-    pub counter: serde_json::Value,
-    /// the end time for the listing
-    #[serde(with = "timestamp_to_date")]
-    pub end_time: DateTime<Utc>,
+    /// the counter of the offerer at the time the order was signed
+    #[serde(with = "u256_fromstr_or_number")]
+    pub counter: U256,
+    /// the end time for the listing, as unix timestamp. See [`Parameters::end_date`].
+    #[serde(with = "u256_fromstr_or_number")]
+    pub end_time: U256,
     /// the offer object itself
     pub offer: Vec<Offer>,
     /// the offerer
-    pub offerer: Address,
-    /// the OS order type
-    pub order_type: u64,
+    pub offerer: H160,
+    /// the Seaport order type
+    pub order_type: OrderType,
     /// random salt
-    pub salt: String,
-    /// the start time of the listing
-    #[serde(with = "timestamp_to_date")]
-    pub start_time: DateTime<Utc>,
+    #[serde(with = "u256_fromstr_hex_or_dec")]
+    pub salt: U256,
+    /// the start time of the listing, as unix timestamp. See [`Parameters::start_date`].
+    #[serde(with = "u256_fromstr_or_number")]
+    pub start_time: U256,
     /// the amount of consideration items
     pub total_original_consideration_items: u64,
     /// the zone for the execution (post execution evaluation)
-    pub zone: Address,
+    pub zone: H160,
     /// the hash of the given zone
-    pub zone_hash: String,
+    pub zone_hash: H256,
+}
+
+impl Parameters {
+    /// Start time as date. `None` if it is out of the range of [`DateTime`].
+    pub fn start_date(&self) -> Option<DateTime<Utc>> {
+        u256_to_date(self.start_time)
+    }
+
+    /// End time as date. `None` if it is out of the range of [`DateTime`], e.g. for orders
+    /// which never expire.
+    pub fn end_date(&self) -> Option<DateTime<Utc>> {
+        u256_to_date(self.end_time)
+    }
+}
+
+fn u256_to_date(timestamp: U256) -> Option<DateTime<Utc>> {
+    let timestamp = i64::try_from(u64::try_from(timestamp).ok()?).ok()?;
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
 }
 
 /// a consideration item for an offer
@@ -717,17 +1419,20 @@ pub struct Parameters {
 #[serde(rename_all = "camelCase")]
 pub struct Consideration {
     /// the type of the given transfer
-    pub item_type: u64,
+    pub item_type: ItemType,
     /// the address of the offered item
-    pub token: Address,
+    pub token: H160,
     /// the identifier or criteria of the offer
-    pub identifier_or_criteria: String,
+    #[serde(with = "u256_fromstr_radix_10")]
+    pub identifier_or_criteria: U256,
     /// the min amount to transfer to the recipient
-    pub start_amount: String,
+    #[serde(with = "u256_fromstr_radix_10")]
+    pub start_amount: U256,
     /// the max amount to transfer to the recipient
-    pub end_amount: Option<String>,
+    #[serde(with = "u256_fromstr_radix_10_opt", default)]
+    pub end_amount: Option<U256>,
     /// the recipient of this transfer
-    pub recipient: Address,
+    pub recipient: H160,
 }
 
 /// the offer object within the protocol data
@@ -735,55 +1440,135 @@ pub struct Consideration {
 #[serde(rename_all = "camelCase")]
 pub struct Offer {
     /// the max amount of the offer
-    pub end_amount: String,
+    #[serde(with = "u256_fromstr_radix_10")]
+    pub end_amount: U256,
     /// the identifier or criteria of the offer
-    pub identifier_or_criteria: String,
+    #[serde(with = "u256_fromstr_radix_10")]
+    pub identifier_or_criteria: U256,
     /// the type of the offered item
-    pub item_type: u64,
+    pub item_type: ItemType,
     /// the min amount of the offer
-    pub start_amount: String,
+    #[serde(with = "u256_fromstr_radix_10")]
+    pub start_amount: U256,
     /// the address of the offered item
-    pub token: Address,
+    pub token: H160,
+}
+
+/// Type of an offered or considered item in a Seaport order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum ItemType {
+    /// The native currency of the chain, e.g. ETH.
+    Native = 0,
+    /// An ERC-20 token.
+    Erc20 = 1,
+    /// An ERC-721 token.
+    Erc721 = 2,
+    /// An ERC-1155 token.
+    Erc1155 = 3,
+    /// Any ERC-721 token matching a merkle root of token ids.
+    Erc721WithCriteria = 4,
+    /// Any ERC-1155 token matching a merkle root of token ids.
+    Erc1155WithCriteria = 5,
+}
+
+impl TryFrom<u8> for ItemType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ItemType::Native),
+            1 => Ok(ItemType::Erc20),
+            2 => Ok(ItemType::Erc721),
+            3 => Ok(ItemType::Erc1155),
+            4 => Ok(ItemType::Erc721WithCriteria),
+            5 => Ok(ItemType::Erc1155WithCriteria),
+            _ => Err(format!("invalid item type {}", value)),
+        }
+    }
+}
+
+impl From<ItemType> for u8 {
+    fn from(value: ItemType) -> Self {
+        value as u8
+    }
+}
+
+/// Type of a Seaport order.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum OrderType {
+    /// No partial fills, anyone can execute.
+    FullOpen = 0,
+    /// Partial fills supported, anyone can execute.
+    PartialOpen = 1,
+    /// No partial fills, only offerer or zone can execute.
+    FullRestricted = 2,
+    /// Partial fills supported, only offerer or zone can execute.
+    PartialRestricted = 3,
+    /// Offer and consideration are generated by a contract offerer.
+    Contract = 4,
+}
+
+impl TryFrom<u8> for OrderType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(OrderType::FullOpen),
+            1 => Ok(OrderType::PartialOpen),
+            2 => Ok(OrderType::FullRestricted),
+            3 => Ok(OrderType::PartialRestricted),
+            4 => Ok(OrderType::Contract),
+            _ => Err(format!("invalid order type {}", value)),
+        }
+    }
+}
+
+impl From<OrderType> for u8 {
+    fn from(value: OrderType) -> Self {
+        value as u8
+    }
 }
 
 mod address_fromjson {
-    use ethers_core::abi::Address;
+    use super::Address;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
     #[derive(Serialize, Deserialize)]
-    struct Inner {
-        address: Address,
+    struct Inner<A> {
+        address: A,
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Address, D::Error>
     where
         D: Deserializer<'de>,
     {
-        Deserialize::deserialize(deserializer).map(|v: Inner| v.address)
+        Deserialize::deserialize(deserializer).map(|v: Inner<Address>| v.address)
     }
 
     pub fn serialize<S>(value: &Address, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        Inner { address: *value }.serialize(serializer)
+        Inner { address: value }.serialize(serializer)
     }
 }
 
 mod address_fromjson_opt {
-    use ethers_core::abi::Address;
+    use super::Address;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
     #[derive(Serialize, Deserialize)]
-    struct Inner {
-        address: Address,
+    struct Inner<A> {
+        address: A,
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Address>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let inner: Option<Inner> = Deserialize::deserialize(deserializer)?;
+        let inner: Option<Inner<Address>> = Deserialize::deserialize(deserializer)?;
         Ok(inner.map(|i| i.address))
     }
 
@@ -791,7 +1576,10 @@ mod address_fromjson_opt {
     where
         S: Serializer,
     {
-        value.map(|v| Inner { address: v }).serialize(serializer)
+        value
+            .as_ref()
+            .map(|v| Inner { address: v })
+            .serialize(serializer)
     }
 }
 
@@ -833,39 +1621,88 @@ mod u256_fromstr_radix_10 {
     }
 }
 
-mod f64_fromstring {
-    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+mod u256_fromstr_radix_10_opt {
+    use super::*;
+    use serde::{Deserializer, Serializer};
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<U256>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Inner(#[serde(with = "super::u256_fromstr_radix_10")] U256);
+
+        let inner: Option<Inner> = Deserialize::deserialize(deserializer)?;
+        Ok(inner.map(|v| v.0))
+    }
+
+    pub fn serialize<S>(value: &Option<U256>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+mod u256_fromstr_or_number {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<U256, D::Error>
     where
         D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
         #[serde(untagged)]
-        enum StringFloat {
+        enum StringNumber {
             Str(String),
-            F64(f64),
+            U64(u64),
         }
 
-        match StringFloat::deserialize(deserializer)? {
-            StringFloat::Str(s) => s.parse().map_err(D::Error::custom),
-            StringFloat::F64(f) => Ok(f),
+        match StringNumber::deserialize(deserializer)? {
+            StringNumber::Str(s) => U256::from_dec_str(&s).map_err(D::Error::custom),
+            StringNumber::U64(n) => Ok(U256::from(n)),
         }
     }
 
-    pub fn serialize<S>(value: &f64, serializer: S) -> Result<S::Ok, S::Error>
+    pub fn serialize<S>(value: &U256, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        value.to_string().serialize(serializer)
+        serializer.collect_str(value)
     }
 }
 
-mod timestamp_to_date {
-    use chrono::{DateTime, Utc};
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+mod u256_fromstr_hex_or_dec {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<U256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        match value.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).map_err(D::Error::custom),
+            None => U256::from_dec_str(&value).map_err(D::Error::custom),
+        }
+    }
+
+    pub fn serialize<S>(value: &U256, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&format_args!("{:#x}", value))
+    }
+}
+
+mod f64_fromstring {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f64, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -873,23 +1710,110 @@ mod timestamp_to_date {
         #[serde(untagged)]
         enum StringFloat {
             Str(String),
-            Datetime(DateTime<Utc>),
+            F64(f64),
         }
 
         match StringFloat::deserialize(deserializer)? {
-            StringFloat::Datetime(value) => Ok(value),
-            StringFloat::Str(value) => {
-                let nt = chrono::NaiveDateTime::from_timestamp_opt(value.parse().unwrap(), 0);
-                let datetime = DateTime::<Utc>::from_utc(nt.unwrap(), Utc);
-                Ok(datetime)
-            }
+            StringFloat::Str(s) => s.parse().map_err(D::Error::custom),
+            StringFloat::F64(f) => Ok(f),
         }
     }
 
-    pub fn serialize<S>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    pub fn serialize<S>(value: &f64, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        value.timestamp().to_string().serialize(serializer)
+        value.to_string().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Payload, StreamEvent};
+    use serde_json::{json, Value};
+
+    fn transfer(edit: impl FnOnce(&mut Value)) -> String {
+        let mut payload = json!({
+            "collection": { "slug": "foo" },
+            "event_timestamp": "2024-03-01T12:00:00+00:00",
+            "from_account": { "address": "0x1111111111111111111111111111111111111111" },
+            "item": {
+                "chain": { "name": "ethereum" },
+                "metadata": null,
+                "nft_id": "ethereum/0xbd3531da5cf5857e7cfaa92426877b022e612cf8/1",
+                "permalink": null
+            },
+            "to_account": { "address": "0x2222222222222222222222222222222222222222" },
+            "transaction": {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "timestamp": "2024-03-01T12:00:00+00:00"
+            }
+        });
+        edit(&mut payload);
+        json!({
+            "event_type": "item_transferred",
+            "sent_at": "2024-03-01T12:00:01+00:00",
+            "payload": payload
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn lenient_replaces_optional_field() {
+        let event =
+            StreamEvent::from_str_lenient(&transfer(|p| p["item"]["chain"] = json!(7))).unwrap();
+
+        let Payload::ItemTransferred(data) = &event.payload else {
+            panic!("unexpected payload {:?}", event.payload)
+        };
+        assert_eq!(data.item.chain, None);
+        assert!(data.item.nft_id.is_some());
+        assert_eq!(event.warnings.len(), 1);
+        assert_eq!(event.warnings[0].path, "payload.item.chain");
+        assert_eq!(event.warnings[0].raw, json!(7));
+    }
+
+    #[test]
+    fn lenient_replaces_optional_parent_of_required_field() {
+        let event = StreamEvent::from_str_lenient(&transfer(|p| {
+            p["transaction"]["hash"] = json!("not a hash")
+        }))
+        .unwrap();
+
+        let Payload::ItemTransferred(data) = &event.payload else {
+            panic!("unexpected payload {:?}", event.payload)
+        };
+        assert!(data.transaction.is_none());
+        assert_eq!(event.warnings.len(), 1);
+        assert_eq!(event.warnings[0].path, "payload.transaction");
+        assert_eq!(
+            event.warnings[0].raw,
+            json!({
+                "hash": "not a hash",
+                "timestamp": "2024-03-01T12:00:00+00:00"
+            })
+        );
+    }
+
+    #[test]
+    fn lenient_keeps_unrecoverable_payload() {
+        let event = StreamEvent::from_str_lenient(&transfer(|p| {
+            p["item"]["chain"] = json!(7);
+            p["event_timestamp"] = json!("yesterday");
+        }))
+        .unwrap();
+
+        let Payload::Unknown {
+            event_type,
+            payload,
+        } = &event.payload
+        else {
+            panic!("unexpected payload {:?}", event.payload)
+        };
+        assert_eq!(event_type, "item_transferred");
+        assert_eq!(payload["item"]["chain"], json!(7));
+        assert_eq!(event.warnings.len(), 1);
+        assert_eq!(event.warnings[0].path, "payload.event_timestamp");
+        assert_eq!(event.warnings[0].raw, json!("yesterday"));
     }
 }
@@ -0,0 +1,755 @@
+//! Seaport orders reconstructed from [`ProtocolData`].
+//!
+//! The types mirror the structs of the [Seaport](https://github.com/ProjectOpenSea/seaport) contracts,
+//! so that orders received from the stream can be hashed, ABI encoded and fulfilled on chain.
+use crate::schema::{
+    CollectionOfferData, Consideration, ItemListedData, ItemType, Offer, OrderType, Parameters,
+    ProtocolData, TraitOfferData,
+};
+use ethers_core::{
+    abi::{encode, Token},
+    types::{Bytes, ParseBytesError, Signature, H160, H256, U256},
+    utils::{id, keccak256},
+};
+use std::fmt::Display;
+
+/// Address of Seaport 1.5, which is the same on every supported chain.
+pub const SEAPORT_1_5: H160 = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xad, 0xc0, 0x4c, 0x56, 0xbf, 0x30, 0xac, 0x9d, 0x3c,
+    0x0a, 0xaf, 0x14, 0xdc,
+]);
+
+/// Address of Seaport 1.6, which is the same on every supported chain.
+pub const SEAPORT_1_6: H160 = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x68, 0xf1, 0x16, 0xa8, 0x94, 0x98, 0x4e, 0x2d, 0xb1,
+    0x12, 0x3e, 0xb3, 0x95,
+]);
+
+const OFFER_ITEM_TYPE: &str = "OfferItem(uint8 itemType,address token,uint256 identifierOrCriteria,uint256 startAmount,uint256 endAmount)";
+
+const CONSIDERATION_ITEM_TYPE: &str = "ConsiderationItem(uint8 itemType,address token,uint256 identifierOrCriteria,uint256 startAmount,uint256 endAmount,address recipient)";
+
+const ORDER_COMPONENTS_TYPE: &str = "OrderComponents(address offerer,address zone,OfferItem[] offer,ConsiderationItem[] consideration,uint8 orderType,uint256 startTime,uint256 endTime,bytes32 zoneHash,uint256 salt,bytes32 conduitKey,uint256 counter)";
+
+const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// Solidity signature of `fulfillOrder`.
+const FULFILL_ORDER: &str = "fulfillOrder(((address,address,(uint8,address,uint256,uint256,uint256)[],(uint8,address,uint256,uint256,uint256,address)[],uint8,uint256,uint256,bytes32,uint256,bytes32,uint256),bytes),bytes32)";
+
+/// Solidity signature of `fulfillAdvancedOrder`.
+const FULFILL_ADVANCED_ORDER: &str = "fulfillAdvancedOrder(((address,address,(uint8,address,uint256,uint256,uint256)[],(uint8,address,uint256,uint256,uint256,address)[],uint8,uint256,uint256,bytes32,uint256,bytes32,uint256),uint120,uint120,bytes,bytes),(uint256,uint8,uint256,uint256,bytes32[])[],bytes32,address)";
+
+/// Item offered by the offerer of an order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfferItem {
+    pub item_type: ItemType,
+    pub token: H160,
+    pub identifier_or_criteria: U256,
+    pub start_amount: U256,
+    pub end_amount: U256,
+}
+
+impl OfferItem {
+    /// EIP-712 struct hash of the item.
+    pub fn hash(&self) -> H256 {
+        struct_hash(OFFER_ITEM_TYPE, self.to_token())
+    }
+
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(u8::from(self.item_type).into()),
+            Token::Address(self.token),
+            Token::Uint(self.identifier_or_criteria),
+            Token::Uint(self.start_amount),
+            Token::Uint(self.end_amount),
+        ])
+    }
+}
+
+impl From<&Offer> for OfferItem {
+    fn from(offer: &Offer) -> Self {
+        Self {
+            item_type: offer.item_type,
+            token: offer.token,
+            identifier_or_criteria: offer.identifier_or_criteria,
+            start_amount: offer.start_amount,
+            end_amount: offer.end_amount,
+        }
+    }
+}
+
+/// Item the offerer expects to receive in return.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsiderationItem {
+    pub item_type: ItemType,
+    pub token: H160,
+    pub identifier_or_criteria: U256,
+    pub start_amount: U256,
+    pub end_amount: U256,
+    pub recipient: H160,
+}
+
+impl ConsiderationItem {
+    /// EIP-712 struct hash of the item.
+    pub fn hash(&self) -> H256 {
+        struct_hash(CONSIDERATION_ITEM_TYPE, self.to_token())
+    }
+
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(u8::from(self.item_type).into()),
+            Token::Address(self.token),
+            Token::Uint(self.identifier_or_criteria),
+            Token::Uint(self.start_amount),
+            Token::Uint(self.end_amount),
+            Token::Address(self.recipient),
+        ])
+    }
+}
+
+impl From<&Consideration> for ConsiderationItem {
+    /// A missing `end_amount` is taken to be equal to `start_amount`.
+    fn from(consideration: &Consideration) -> Self {
+        Self {
+            item_type: consideration.item_type,
+            token: consideration.token,
+            identifier_or_criteria: consideration.identifier_or_criteria,
+            start_amount: consideration.start_amount,
+            end_amount: consideration
+                .end_amount
+                .unwrap_or(consideration.start_amount),
+            recipient: consideration.recipient,
+        }
+    }
+}
+
+/// Parameters of an order as passed to the fulfillment functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderParameters {
+    pub offerer: H160,
+    pub zone: H160,
+    pub offer: Vec<OfferItem>,
+    pub consideration: Vec<ConsiderationItem>,
+    pub order_type: OrderType,
+    pub start_time: U256,
+    pub end_time: U256,
+    pub zone_hash: H256,
+    pub salt: U256,
+    pub conduit_key: H256,
+    pub total_original_consideration_items: U256,
+}
+
+impl OrderParameters {
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.offerer),
+            Token::Address(self.zone),
+            Token::Array(self.offer.iter().map(OfferItem::to_token).collect()),
+            Token::Array(
+                self.consideration
+                    .iter()
+                    .map(ConsiderationItem::to_token)
+                    .collect(),
+            ),
+            Token::Uint(u8::from(self.order_type).into()),
+            Token::Uint(self.start_time),
+            Token::Uint(self.end_time),
+            Token::FixedBytes(self.zone_hash.as_bytes().to_vec()),
+            Token::Uint(self.salt),
+            Token::FixedBytes(self.conduit_key.as_bytes().to_vec()),
+            Token::Uint(self.total_original_consideration_items),
+        ])
+    }
+
+    /// Amount of the native currency that has to be sent along with a fulfillment.
+    ///
+    /// Uses the larger of the start and end amount, so that it also covers ascending auctions.
+    pub fn native_value(&self) -> U256 {
+        self.consideration
+            .iter()
+            .filter(|item| item.item_type == ItemType::Native)
+            .fold(U256::zero(), |sum, item| {
+                sum + item.start_amount.max(item.end_amount)
+            })
+    }
+}
+
+impl From<&Parameters> for OrderParameters {
+    fn from(parameters: &Parameters) -> Self {
+        Self {
+            offerer: parameters.offerer,
+            zone: parameters.zone,
+            offer: parameters.offer.iter().map(OfferItem::from).collect(),
+            consideration: parameters
+                .consideration
+                .iter()
+                .map(ConsiderationItem::from)
+                .collect(),
+            order_type: parameters.order_type,
+            start_time: parameters.start_time,
+            end_time: parameters.end_time,
+            zone_hash: parameters.zone_hash,
+            salt: parameters.salt,
+            conduit_key: parameters.conduit_key,
+            total_original_consideration_items: parameters
+                .total_original_consideration_items
+                .into(),
+        }
+    }
+}
+
+/// Order as signed by the offerer. Same as [`OrderParameters`], but with the offerer's `counter`
+/// in place of `total_original_consideration_items`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderComponents {
+    pub offerer: H160,
+    pub zone: H160,
+    pub offer: Vec<OfferItem>,
+    pub consideration: Vec<ConsiderationItem>,
+    pub order_type: OrderType,
+    pub start_time: U256,
+    pub end_time: U256,
+    pub zone_hash: H256,
+    pub salt: U256,
+    pub conduit_key: H256,
+    pub counter: U256,
+}
+
+impl OrderComponents {
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.offerer),
+            Token::Address(self.zone),
+            Token::Array(self.offer.iter().map(OfferItem::to_token).collect()),
+            Token::Array(
+                self.consideration
+                    .iter()
+                    .map(ConsiderationItem::to_token)
+                    .collect(),
+            ),
+            Token::Uint(u8::from(self.order_type).into()),
+            Token::Uint(self.start_time),
+            Token::Uint(self.end_time),
+            Token::FixedBytes(self.zone_hash.as_bytes().to_vec()),
+            Token::Uint(self.salt),
+            Token::FixedBytes(self.conduit_key.as_bytes().to_vec()),
+            Token::Uint(self.counter),
+        ])
+    }
+
+    /// ABI encoding of the struct.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        encode(&[self.to_token()])
+    }
+
+    /// Seaport order hash, i.e. the EIP-712 struct hash of the components.
+    ///
+    /// This is the `order_hash` reported by OpenSea and returned by `getOrderHash`. It does not
+    /// depend on the chain or Seaport deployment, see [`OrderComponents::digest`] for the value
+    /// that is actually signed.
+    pub fn hash(&self) -> H256 {
+        let type_string = format!(
+            "{}{}{}",
+            ORDER_COMPONENTS_TYPE, CONSIDERATION_ITEM_TYPE, OFFER_ITEM_TYPE
+        );
+        let offer: Vec<u8> = self
+            .offer
+            .iter()
+            .flat_map(|item| item.hash().to_fixed_bytes())
+            .collect();
+        let consideration: Vec<u8> = self
+            .consideration
+            .iter()
+            .flat_map(|item| item.hash().to_fixed_bytes())
+            .collect();
+
+        struct_hash(
+            &type_string,
+            Token::Tuple(vec![
+                Token::Address(self.offerer),
+                Token::Address(self.zone),
+                Token::FixedBytes(keccak256(offer).to_vec()),
+                Token::FixedBytes(keccak256(consideration).to_vec()),
+                Token::Uint(u8::from(self.order_type).into()),
+                Token::Uint(self.start_time),
+                Token::Uint(self.end_time),
+                Token::FixedBytes(self.zone_hash.as_bytes().to_vec()),
+                Token::Uint(self.salt),
+                Token::FixedBytes(self.conduit_key.as_bytes().to_vec()),
+                Token::Uint(self.counter),
+            ]),
+        )
+    }
+
+    /// EIP-712 digest of the order on the given Seaport deployment, which is what the offerer signs.
+    pub fn digest(&self, domain: &Domain) -> H256 {
+        let mut message = vec![0x19, 0x01];
+        message.extend(domain.separator().as_bytes());
+        message.extend(self.hash().as_bytes());
+        keccak256(message).into()
+    }
+}
+
+/// EIP-712 domain of a Seaport deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Domain {
+    /// Name of the contract, always `Seaport`.
+    pub name: String,
+    /// Version of the deployment, e.g. `1.6`.
+    pub version: String,
+    /// EIP-155 id of the chain the contract is deployed on.
+    pub chain_id: u64,
+    /// Address of the contract.
+    pub verifying_contract: H160,
+}
+
+impl Domain {
+    /// Domain of a Seaport deployment.
+    pub fn new(version: &str, chain_id: u64, verifying_contract: H160) -> Self {
+        Self {
+            name: "Seaport".to_string(),
+            version: version.to_string(),
+            chain_id,
+            verifying_contract,
+        }
+    }
+
+    /// Domain of a canonical Seaport deployment, given its address (e.g. an event's `protocol_address`).
+    ///
+    /// Returns `None` for unknown deployments.
+    pub fn from_address(chain_id: u64, verifying_contract: H160) -> Option<Self> {
+        if verifying_contract == SEAPORT_1_5 {
+            Some(Self::seaport_1_5(chain_id))
+        } else if verifying_contract == SEAPORT_1_6 {
+            Some(Self::seaport_1_6(chain_id))
+        } else {
+            None
+        }
+    }
+
+    /// Domain of the canonical Seaport 1.5 deployment.
+    pub fn seaport_1_5(chain_id: u64) -> Self {
+        Self::new("1.5", chain_id, SEAPORT_1_5)
+    }
+
+    /// Domain of the canonical Seaport 1.6 deployment, used by OpenSea for new orders.
+    pub fn seaport_1_6(chain_id: u64) -> Self {
+        Self::new("1.6", chain_id, SEAPORT_1_6)
+    }
+
+    /// EIP-712 domain separator.
+    pub fn separator(&self) -> H256 {
+        struct_hash(
+            EIP712_DOMAIN_TYPE,
+            Token::Tuple(vec![
+                Token::FixedBytes(keccak256(&self.name).to_vec()),
+                Token::FixedBytes(keccak256(&self.version).to_vec()),
+                Token::Uint(self.chain_id.into()),
+                Token::Address(self.verifying_contract),
+            ]),
+        )
+    }
+}
+
+impl From<&Parameters> for OrderComponents {
+    fn from(parameters: &Parameters) -> Self {
+        let order = OrderParameters::from(parameters);
+        Self {
+            offerer: order.offerer,
+            zone: order.zone,
+            offer: order.offer,
+            consideration: order.consideration,
+            order_type: order.order_type,
+            start_time: order.start_time,
+            end_time: order.end_time,
+            zone_hash: order.zone_hash,
+            salt: order.salt,
+            conduit_key: order.conduit_key,
+            counter: parameters.counter,
+        }
+    }
+}
+
+/// Order with its signature, as passed to `fulfillOrder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub parameters: OrderParameters,
+    pub signature: Bytes,
+}
+
+impl Order {
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            self.parameters.to_token(),
+            Token::Bytes(self.signature.to_vec()),
+        ])
+    }
+
+    /// ABI encoding of the struct.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        encode(&[self.to_token()])
+    }
+
+    /// Calldata for `fulfillOrder`, spending the fulfiller's approvals on the given conduit.
+    ///
+    /// Use `H256::zero()` to have tokens transferred by Seaport directly.
+    pub fn fulfill_calldata(&self, fulfiller_conduit_key: H256) -> Bytes {
+        calldata(
+            FULFILL_ORDER,
+            &[
+                self.to_token(),
+                Token::FixedBytes(fulfiller_conduit_key.as_bytes().to_vec()),
+            ],
+        )
+    }
+}
+
+/// Order that can be partially filled and carry extra data for the zone, as passed to `fulfillAdvancedOrder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdvancedOrder {
+    pub parameters: OrderParameters,
+    pub numerator: U256,
+    pub denominator: U256,
+    pub signature: Bytes,
+    pub extra_data: Bytes,
+}
+
+impl AdvancedOrder {
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            self.parameters.to_token(),
+            Token::Uint(self.numerator),
+            Token::Uint(self.denominator),
+            Token::Bytes(self.signature.to_vec()),
+            Token::Bytes(self.extra_data.to_vec()),
+        ])
+    }
+
+    /// ABI encoding of the struct.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        encode(&[self.to_token()])
+    }
+
+    /// Calldata for `fulfillAdvancedOrder`, sending the received items to `recipient`.
+    ///
+    /// Use `H256::zero()` as conduit key to have tokens transferred by Seaport directly,
+    /// and `H160::zero()` as recipient to receive the items as the caller.
+    pub fn fulfill_calldata(
+        &self,
+        criteria_resolvers: &[CriteriaResolver],
+        fulfiller_conduit_key: H256,
+        recipient: H160,
+    ) -> Bytes {
+        calldata(
+            FULFILL_ADVANCED_ORDER,
+            &[
+                self.to_token(),
+                Token::Array(
+                    criteria_resolvers
+                        .iter()
+                        .map(CriteriaResolver::to_token)
+                        .collect(),
+                ),
+                Token::FixedBytes(fulfiller_conduit_key.as_bytes().to_vec()),
+                Token::Address(recipient),
+            ],
+        )
+    }
+}
+
+impl From<Order> for AdvancedOrder {
+    /// Fills the whole order without extra data.
+    fn from(order: Order) -> Self {
+        Self {
+            parameters: order.parameters,
+            numerator: U256::one(),
+            denominator: U256::one(),
+            signature: order.signature,
+            extra_data: Bytes::default(),
+        }
+    }
+}
+
+/// Side of an order a [`CriteriaResolver`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Offer = 0,
+    Consideration = 1,
+}
+
+/// Resolves a criteria based item to a specific token id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriteriaResolver {
+    pub order_index: U256,
+    pub side: Side,
+    pub index: U256,
+    pub identifier: U256,
+    pub criteria_proof: Vec<H256>,
+}
+
+impl CriteriaResolver {
+    /// ABI token of the struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(self.order_index),
+            Token::Uint((self.side as u8).into()),
+            Token::Uint(self.index),
+            Token::Uint(self.identifier),
+            Token::Array(
+                self.criteria_proof
+                    .iter()
+                    .map(|node| Token::FixedBytes(node.as_bytes().to_vec()))
+                    .collect(),
+            ),
+        ])
+    }
+}
+
+impl ProtocolData {
+    /// Order components as signed by the offerer.
+    pub fn order_components(&self) -> OrderComponents {
+        OrderComponents::from(&self.parameters)
+    }
+
+    /// Seaport order hash computed from the parameters.
+    pub fn order_hash(&self) -> H256 {
+        self.order_components().hash()
+    }
+
+    /// Order ready to be passed to `fulfillOrder`.
+    ///
+    /// A missing signature results in an empty one, which Seaport accepts for orders validated on chain.
+    pub fn order(&self) -> Result<Order, ParseBytesError> {
+        let signature = match &self.signature {
+            Some(signature) => signature.parse()?,
+            None => Bytes::default(),
+        };
+
+        Ok(Order {
+            parameters: OrderParameters::from(&self.parameters),
+            signature,
+        })
+    }
+
+    /// Order ready to be passed to `fulfillAdvancedOrder`, filling the order completely.
+    pub fn advanced_order(&self) -> Result<AdvancedOrder, ParseBytesError> {
+        self.order().map(AdvancedOrder::from)
+    }
+
+    /// Decodes the signature, either 65 bytes long or in the compact form of
+    /// [EIP-2098](https://eips.ethereum.org/EIPS/eip-2098).
+    pub fn decode_signature(&self) -> Result<Signature, SignatureError> {
+        let signature: Bytes = self
+            .signature
+            .as_deref()
+            .ok_or(SignatureError::Missing)?
+            .parse()
+            .map_err(SignatureError::Hex)?;
+        decode_signature(&signature)
+    }
+
+    /// Recovers the address that signed the order on the given Seaport deployment.
+    pub fn recover_signer(&self, domain: &Domain) -> Result<H160, SignatureError> {
+        let digest = self.order_components().digest(domain);
+        self.decode_signature()?
+            .recover(digest)
+            .map_err(SignatureError::Recovery)
+    }
+
+    /// Whether the order was signed by its offerer.
+    ///
+    /// Orders of contract offerers and smart contract wallets (EIP-1271) are validated on chain
+    /// and never verify here.
+    pub fn verify_signature(&self, domain: &Domain) -> Result<bool, SignatureError> {
+        Ok(self.recover_signer(domain)? == self.parameters.offerer)
+    }
+}
+
+/// Error returned when the signature of an order can't be recovered.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SignatureError {
+    /// The order has no signature.
+    Missing,
+    /// The signature is not valid hex.
+    Hex(ParseBytesError),
+    /// The order was signed as part of a bulk order, which is not supported.
+    BulkOrder,
+    /// The signature has an unexpected length.
+    InvalidLength(usize),
+    /// The signer could not be recovered.
+    Recovery(ethers_core::types::SignatureError),
+}
+
+impl Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Missing => write!(f, "order has no signature"),
+            SignatureError::Hex(e) => write!(f, "invalid signature: {}", e),
+            SignatureError::BulkOrder => write!(f, "bulk order signatures are not supported"),
+            SignatureError::InvalidLength(len) => {
+                write!(f, "invalid signature length {}, expected 64 or 65", len)
+            }
+            SignatureError::Recovery(e) => write!(f, "failed to recover signer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SignatureError::Hex(e) => Some(e),
+            SignatureError::Recovery(e) => Some(e),
+            SignatureError::Missing
+            | SignatureError::BulkOrder
+            | SignatureError::InvalidLength(_) => None,
+        }
+    }
+}
+
+/// Decodes a 65 byte `r ++ s ++ v` signature or a 64 byte EIP-2098 `r ++ yParityAndS` signature.
+pub fn decode_signature(bytes: &[u8]) -> Result<Signature, SignatureError> {
+    match bytes.len() {
+        65 => Signature::try_from(bytes).map_err(SignatureError::Recovery),
+        64 => {
+            let r = U256::from_big_endian(&bytes[..32]);
+            let y_parity_and_s = U256::from_big_endian(&bytes[32..]);
+            let y_parity = y_parity_and_s.bit(255);
+            let s = y_parity_and_s & (U256::MAX >> 1);
+            Ok(Signature {
+                r,
+                s,
+                v: 27 + y_parity as u64,
+            })
+        }
+        // A bulk signature is followed by a 3 byte key and a merkle proof of 32 byte nodes.
+        len if [64, 65].into_iter().any(|signature| {
+            len.checked_sub(signature + 3)
+                .is_some_and(|proof| proof > 0 && proof % 32 == 0)
+        }) =>
+        {
+            Err(SignatureError::BulkOrder)
+        }
+        len => Err(SignatureError::InvalidLength(len)),
+    }
+}
+
+macro_rules! impl_verify_order {
+    ($($data:ty),*) => {
+        $(
+            impl $data {
+                /// Whether `order_hash` matches the hash computed from `protocol_data`.
+                pub fn verify_order_hash(&self) -> bool {
+                    self.protocol_data.order_hash() == self.order_hash
+                }
+
+                /// Whether the order was signed by both its offerer and the event's `maker`.
+                pub fn verify_signature(&self, domain: &Domain) -> Result<bool, SignatureError> {
+                    let signer = self.protocol_data.recover_signer(domain)?;
+                    Ok(signer == self.protocol_data.parameters.offerer && self.maker == signer)
+                }
+            }
+        )*
+    };
+}
+
+impl_verify_order!(ItemListedData, CollectionOfferData, TraitOfferData);
+
+/// `keccak256(typeHash ++ encodeData)` where the fields of `data` are already EIP-712 encoded.
+fn struct_hash(type_string: &str, data: Token) -> H256 {
+    let Token::Tuple(mut fields) = data else {
+        unreachable!("struct data is always a tuple")
+    };
+    fields.insert(0, Token::FixedBytes(keccak256(type_string).to_vec()));
+    keccak256(encode(&fields)).into()
+}
+
+fn calldata(signature: &str, tokens: &[Token]) -> Bytes {
+    let mut data = id(signature).to_vec();
+    data.extend(encode(tokens));
+    data.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_signature, Domain, SignatureError};
+    use crate::schema::{ItemListedData, Payload, StreamEvent};
+
+    /// A Seaport 1.6 listing on mainnet, signed with the first default Hardhat account. Its
+    /// `order_hash` was computed with the `TypedData` implementation of ethers.
+    fn listing() -> ItemListedData {
+        let event: StreamEvent =
+            serde_json::from_str(include_str!("testdata/item_listed.json")).unwrap();
+        match event.payload {
+            Payload::ItemListed(data) => data,
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+    }
+
+    #[test]
+    fn order_hash() {
+        let listing = listing();
+        assert!(listing.verify_order_hash());
+        assert_eq!(listing.protocol_data.order_hash(), listing.order_hash);
+    }
+
+    #[test]
+    fn recover_signer() {
+        let mut listing = listing();
+        let domain = Domain::seaport_1_6(1);
+        let offerer = listing.protocol_data.parameters.offerer;
+        assert_eq!(
+            listing.protocol_data.recover_signer(&domain).unwrap(),
+            offerer
+        );
+        assert!(listing.verify_signature(&domain).unwrap());
+
+        // signed for Seaport 1.6, so the signer differs on any other domain
+        let other = Domain::seaport_1_5(1);
+        assert_ne!(
+            listing.protocol_data.recover_signer(&other).unwrap(),
+            offerer
+        );
+        assert!(!listing.verify_signature(&other).unwrap());
+
+        // EIP-2098 form of the same signature, whose `v` is 27
+        let signature = listing.protocol_data.signature.as_mut().unwrap();
+        signature.truncate(2 + 64 * 2);
+        assert_eq!(
+            listing.protocol_data.recover_signer(&domain).unwrap(),
+            offerer
+        );
+        assert!(listing.verify_signature(&domain).unwrap());
+    }
+
+    #[test]
+    fn signature_lengths() {
+        assert!(decode_signature(&[0u8; 65]).is_ok());
+
+        let compact = decode_signature(&[0xffu8; 64]).unwrap();
+        assert_eq!(compact.v, 28);
+
+        for len in [0, 63, 66, 67, 68, 101] {
+            assert!(matches!(
+                decode_signature(&vec![0u8; len]),
+                Err(SignatureError::InvalidLength(l)) if l == len
+            ));
+        }
+
+        for proof in [32, 64, 24 * 32] {
+            for len in [67 + proof, 68 + proof] {
+                assert!(matches!(
+                    decode_signature(&vec![0u8; len]),
+                    Err(SignatureError::BulkOrder)
+                ));
+            }
+        }
+    }
+}
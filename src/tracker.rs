@@ -0,0 +1,518 @@
+//! Floor prices and best offers derived from the [`OrderBook`].
+use crate::{
+    orderbook::{Change, OrderBook, OrderEntry, OrderKind, Removal},
+    price::{Decimal, Priced},
+    schema::{NftId, Payload, StreamEvent},
+};
+use chrono::{DateTime, Utc};
+use ethers_core::types::H256;
+use std::collections::{HashMap, HashSet};
+
+/// Best order on one side of a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    /// Hash id of the order.
+    pub order_hash: H256,
+    /// Price per item, denominated in ETH.
+    pub price: Decimal,
+}
+
+impl Quote {
+    fn of(order: &OrderEntry) -> Option<Self> {
+        Some(Self {
+            order_hash: order.order_hash,
+            price: order.unit_price_in_eth()?,
+        })
+    }
+}
+
+/// Side of a market a [`QuoteUpdate`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuoteKind {
+    /// Cheapest listing.
+    Floor,
+    /// Highest offer.
+    BestOffer,
+}
+
+/// Notification that the price of a quote moved, or that it appeared or disappeared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteUpdate<K> {
+    /// Market the quote belongs to.
+    pub key: K,
+    /// Which quote changed.
+    pub kind: QuoteKind,
+    /// Quote before the change.
+    pub previous: Option<Quote>,
+    /// Quote after the change.
+    pub current: Option<Quote>,
+}
+
+/// Floor and best offer of a market.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Quotes {
+    floor: Option<Quote>,
+    best_offer: Option<Quote>,
+}
+
+impl Quotes {
+    fn updates<K: Clone>(&self, key: &K, current: &Quotes) -> Vec<QuoteUpdate<K>> {
+        let moved = |a: Option<Quote>, b: Option<Quote>| a.map(|q| q.price) != b.map(|q| q.price);
+
+        let mut updates = Vec::new();
+        if moved(self.floor, current.floor) {
+            updates.push(QuoteUpdate {
+                key: key.clone(),
+                kind: QuoteKind::Floor,
+                previous: self.floor,
+                current: current.floor,
+            });
+        }
+        if moved(self.best_offer, current.best_offer) {
+            updates.push(QuoteUpdate {
+                key: key.clone(),
+                kind: QuoteKind::BestOffer,
+                previous: self.best_offer,
+                current: current.best_offer,
+            });
+        }
+        updates
+    }
+}
+
+fn lowest<'a>(orders: impl Iterator<Item = &'a OrderEntry>) -> Option<Quote> {
+    orders
+        .filter_map(Quote::of)
+        .min_by(|a, b| a.price.cmp(&b.price))
+}
+
+fn highest<'a>(orders: impl Iterator<Item = &'a OrderEntry>) -> Option<Quote> {
+    orders
+        .filter_map(Quote::of)
+        .max_by(|a, b| a.price.cmp(&b.price))
+}
+
+/// Tracks the floor listing and the best collection offer of every collection.
+///
+/// Prices are compared per item and in ETH, so that listings in different payment tokens can be
+/// ranked against each other. Orders expire with the `sent_at` of incoming events, or explicitly
+/// through [`FloorTracker::expire`].
+///
+/// To update the floor as soon as the cheapest listing expires, even while no events arrive,
+/// wait for [`FloorTracker::next_expiration`] alongside the stream:
+///
+/// ```no_run
+/// # async fn run(mut client: opensea_stream::client::Client) {
+/// use chrono::Utc;
+/// use futures_util::StreamExt;
+/// use opensea_stream::tracker::FloorTracker;
+/// use std::time::Duration;
+///
+/// let mut tracker = FloorTracker::new();
+/// loop {
+///     let expiration = tracker
+///         .next_expiration()
+///         .map_or(Duration::MAX, |date| (date - Utc::now()).to_std().unwrap_or_default());
+///     let updates = tokio::select! {
+///         event = client.next() => match event {
+///             Some(Ok(event)) => tracker.apply(&event),
+///             Some(Err(_)) => continue,
+///             None => break,
+///         },
+///         _ = tokio::time::sleep(expiration) => tracker.expire(Utc::now()),
+///     };
+///     for update in updates {
+///         println!("{} {:?}: {:?}", update.key, update.kind, update.current);
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FloorTracker {
+    book: OrderBook,
+    quotes: HashMap<String, Quotes>,
+}
+
+impl FloorTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracker with an event and returns the quotes that moved, keyed by collection slug.
+    pub fn apply(&mut self, event: &StreamEvent) -> Vec<QuoteUpdate<String>> {
+        let changes = self.book.apply(event);
+        self.refresh(&changes)
+    }
+
+    /// Removes orders which expired at the given time and returns the quotes that moved.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<QuoteUpdate<String>> {
+        let changes = self.book.remove_expired(now);
+        self.refresh(&changes)
+    }
+
+    /// Earliest expiration date of the tracked orders. Call [`FloorTracker::expire`] once it
+    /// has passed, see the [example](FloorTracker).
+    pub fn next_expiration(&self) -> Option<DateTime<Utc>> {
+        self.book.next_expiration()
+    }
+
+    /// Cheapest listing of a collection.
+    pub fn floor(&self, slug: &str) -> Option<Quote> {
+        self.quotes.get(slug)?.floor
+    }
+
+    /// Highest collection offer of a collection.
+    pub fn best_offer(&self, slug: &str) -> Option<Quote> {
+        self.quotes.get(slug)?.best_offer
+    }
+
+    /// Underlying order book.
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    fn refresh(&mut self, changes: &[Change]) -> Vec<QuoteUpdate<String>> {
+        let slugs: HashSet<&str> = changes
+            .iter()
+            .map(|change| change.order().collection.slug())
+            .collect();
+
+        let mut updates = Vec::new();
+        for slug in slugs {
+            let orders = || self.book.collection_orders(slug);
+            let current = Quotes {
+                floor: lowest(orders().filter(|order| order.kind == OrderKind::Listing)),
+                best_offer: highest(
+                    orders().filter(|order| order.kind == OrderKind::CollectionOffer),
+                ),
+            };
+
+            let slug = slug.to_string();
+            let previous = self.quotes.get(&slug).copied().unwrap_or_default();
+            updates.extend(previous.updates(&slug, &current));
+            if current == Quotes::default() {
+                self.quotes.remove(&slug);
+            } else {
+                self.quotes.insert(slug, current);
+            }
+        }
+        updates
+    }
+}
+
+/// A trait value within a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraitKey {
+    /// Slug of the collection.
+    pub collection: String,
+    /// Type of the trait, e.g. `Background`.
+    pub trait_type: String,
+    /// Value of the trait, e.g. `Blue`.
+    pub value: String,
+}
+
+/// Tracks the floor listing and the best trait offer of every trait value.
+///
+/// Traits of listed items are taken from the item metadata of the listing, and kept up to date
+/// through metadata updates for as long as the item is listed. Prices are compared per item and in
+/// ETH, and orders expire, like in [`FloorTracker`].
+#[derive(Debug, Clone, Default)]
+pub struct TraitTracker {
+    book: OrderBook,
+    traits: HashMap<NftId, HashSet<(String, String)>>,
+    /// Open listings and trait offers per trait value.
+    by_trait: HashMap<TraitKey, HashSet<H256>>,
+    quotes: HashMap<TraitKey, Quotes>,
+}
+
+impl TraitTracker {
+    /// Creates an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracker with an event and returns the quotes that moved.
+    pub fn apply(&mut self, event: &StreamEvent) -> Vec<QuoteUpdate<TraitKey>> {
+        let mut keys = HashSet::new();
+
+        // Learn the traits of newly listed items, and follow metadata updates of listed items.
+        if let (Some(nft_id), Some(collection)) =
+            (event.payload.nft_id(), event.payload.collection())
+        {
+            let listed = matches!(event.payload, Payload::ItemListed(_));
+            let metadata = event
+                .payload
+                .item()
+                .and_then(|item| item.metadata.as_ref())
+                .and_then(|metadata| metadata.traits.as_ref());
+            if let Some(traits) = metadata.filter(|_| listed || self.traits.contains_key(nft_id)) {
+                let traits: HashSet<(String, String)> = traits
+                    .iter()
+                    .filter_map(|t| Some((t.trait_type.clone(), t.value.clone()?)))
+                    .collect();
+                // move the open listings of the item to its new traits
+                let listings: Vec<OrderEntry> = self.book.listings(nft_id).cloned().collect();
+                for order in &listings {
+                    self.unindex(order);
+                }
+                let previous = self.traits.insert(nft_id.clone(), traits.clone());
+                for order in &listings {
+                    self.index(order);
+                }
+                let touched = previous.into_iter().flatten().chain(traits);
+                keys.extend(touched.map(|(trait_type, value)| TraitKey {
+                    collection: collection.slug().to_string(),
+                    trait_type,
+                    value,
+                }));
+            }
+        }
+
+        let changes = self.book.apply(event);
+        keys.extend(self.update_index(&changes));
+        self.forget_unlisted(&changes);
+        self.refresh(keys)
+    }
+
+    /// Removes orders which expired at the given time and returns the quotes that moved.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<QuoteUpdate<TraitKey>> {
+        let changes = self.book.remove_expired(now);
+        let keys = self.update_index(&changes);
+        self.forget_unlisted(&changes);
+        self.refresh(keys)
+    }
+
+    /// Earliest expiration date of the tracked orders. Call [`TraitTracker::expire`] once it
+    /// has passed, like in the [example](FloorTracker) of [`FloorTracker`].
+    pub fn next_expiration(&self) -> Option<DateTime<Utc>> {
+        self.book.next_expiration()
+    }
+
+    /// Cheapest listing of an item with the given trait.
+    pub fn floor(&self, key: &TraitKey) -> Option<Quote> {
+        self.quotes.get(key)?.floor
+    }
+
+    /// Highest trait offer for the given trait.
+    pub fn best_offer(&self, key: &TraitKey) -> Option<Quote> {
+        self.quotes.get(key)?.best_offer
+    }
+
+    /// Underlying order book.
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    /// Trait values an order counts towards: those of the listed item, or the offer's criteria.
+    fn trait_keys(&self, order: &OrderEntry) -> Vec<TraitKey> {
+        let collection = order.collection.slug();
+        match (&order.trait_criteria, &order.nft_id) {
+            (Some(criteria), _) => vec![TraitKey {
+                collection: collection.to_string(),
+                trait_type: criteria.trait_type.clone(),
+                value: criteria.trait_name.clone(),
+            }],
+            (None, Some(nft_id)) if order.is_listing() => {
+                let traits = self.traits.get(nft_id).into_iter().flatten();
+                traits
+                    .map(|(trait_type, value)| TraitKey {
+                        collection: collection.to_string(),
+                        trait_type: trait_type.clone(),
+                        value: value.clone(),
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn index(&mut self, order: &OrderEntry) {
+        for key in self.trait_keys(order) {
+            self.by_trait
+                .entry(key)
+                .or_default()
+                .insert(order.order_hash);
+        }
+    }
+
+    fn unindex(&mut self, order: &OrderEntry) {
+        for key in self.trait_keys(order) {
+            if let Some(hashes) = self.by_trait.get_mut(&key) {
+                hashes.remove(&order.order_hash);
+                if hashes.is_empty() {
+                    self.by_trait.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Applies the changes of the book to the index and returns the trait values they affect.
+    fn update_index(&mut self, changes: &[Change]) -> HashSet<TraitKey> {
+        let mut keys = HashSet::new();
+        for change in changes {
+            match change {
+                Change::Added(order) | Change::PartiallyFilled(order) => self.index(order),
+                Change::Removed { order, .. } => self.unindex(order),
+            }
+            keys.extend(self.trait_keys(change.order()));
+        }
+        keys
+    }
+
+    /// Drops the traits of items which are no longer listed. Invalidated listings keep their
+    /// traits, since they may be revalidated.
+    fn forget_unlisted(&mut self, changes: &[Change]) {
+        for change in changes {
+            if let Change::Removed { order, reason } = change {
+                if *reason == Removal::Invalidated {
+                    continue;
+                }
+                if let Some(nft_id) = &order.nft_id {
+                    let listed = self.book.listings(nft_id).next().is_some()
+                        || self.book.invalidated_listings(nft_id).next().is_some();
+                    if !listed {
+                        self.traits.remove(nft_id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn refresh(&mut self, keys: HashSet<TraitKey>) -> Vec<QuoteUpdate<TraitKey>> {
+        let mut updates = Vec::new();
+        for key in keys {
+            let orders = || {
+                let hashes = self.by_trait.get(&key).into_iter().flatten();
+                hashes.filter_map(|hash| self.book.get(hash))
+            };
+            let current = Quotes {
+                floor: lowest(orders().filter(|order| order.is_listing())),
+                best_offer: highest(orders().filter(|order| order.kind == OrderKind::TraitOffer)),
+            };
+
+            let previous = self.quotes.get(&key).copied().unwrap_or_default();
+            updates.extend(previous.updates(&key, &current));
+            if current == Quotes::default() {
+                self.quotes.remove(&key);
+            } else {
+                self.quotes.insert(key, current);
+            }
+        }
+        updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuoteKind, TraitKey, TraitTracker};
+    use crate::schema::StreamEvent;
+    use ethers_core::types::H256;
+    use serde_json::{json, Value};
+
+    fn event(event_type: &str, metadata: Value, mut payload: Value) -> StreamEvent {
+        payload["collection"] = json!({ "slug": "pudgypenguins" });
+        payload["event_timestamp"] = json!("2024-03-01T12:05:00+00:00");
+        payload["item"] = json!({
+            "chain": { "name": "ethereum" },
+            "metadata": metadata,
+            "nft_id": "ethereum/0xbd3531da5cf5857e7cfaa92426877b022e612cf8/1234",
+            "permalink": null
+        });
+        serde_json::from_value(json!({
+            "event_type": event_type,
+            "sent_at": "2024-03-01T12:05:00+00:00",
+            "payload": payload,
+        }))
+        .unwrap()
+    }
+
+    fn traits(background: &str) -> Value {
+        json!([{ "trait_type": "Background", "value": background }])
+    }
+
+    fn background(value: &str) -> TraitKey {
+        TraitKey {
+            collection: "pudgypenguins".to_string(),
+            trait_type: "Background".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Tracker with a single listing of an item with a blue background.
+    fn listed() -> (TraitTracker, H256) {
+        let mut listing: Value =
+            serde_json::from_str(include_str!("testdata/item_listed.json")).unwrap();
+        listing["payload"]["item"]["metadata"]["traits"] = traits("Blue");
+        let listing: StreamEvent = serde_json::from_value(listing).unwrap();
+
+        let mut tracker = TraitTracker::new();
+        tracker.apply(&listing);
+        (tracker, listing.payload.order_hash().unwrap())
+    }
+
+    #[test]
+    fn metadata_update_moves_listing() {
+        let (mut tracker, order_hash) = listed();
+        assert_eq!(
+            tracker.floor(&background("Blue")).unwrap().order_hash,
+            order_hash
+        );
+
+        let update = event(
+            "item_metadata_updated",
+            json!({ "traits": traits("Red") }),
+            json!({}),
+        );
+        let updates = tracker.apply(&update);
+        assert_eq!(updates.len(), 2);
+        assert!(updates.iter().all(|update| update.kind == QuoteKind::Floor));
+        assert!(tracker.floor(&background("Blue")).is_none());
+        assert_eq!(
+            tracker.floor(&background("Red")).unwrap().order_hash,
+            order_hash
+        );
+        assert_eq!(tracker.by_trait.len(), 1);
+    }
+
+    #[test]
+    fn forget_traits_of_cancelled_invalidated_listing() {
+        let (mut tracker, order_hash) = listed();
+
+        tracker.apply(&event(
+            "order_invalidate",
+            Value::Null,
+            json!({
+                "chain": { "name": "ethereum" },
+                "order_hash": order_hash,
+                "protocol_address": "0x0000000000000068f116a894984e2db1123eb395"
+            }),
+        ));
+        assert!(tracker.floor(&background("Blue")).is_none());
+        assert!(tracker.by_trait.is_empty());
+        assert_eq!(tracker.traits.len(), 1);
+
+        tracker.apply(&event(
+            "item_cancelled",
+            Value::Null,
+            json!({
+                "base_price": "1000000000000000000",
+                "is_private": false,
+                "listing_date": null,
+                "listing_type": null,
+                "order_hash": order_hash,
+                "payment_token": {
+                    "address": "0x0000000000000000000000000000000000000000",
+                    "decimals": 18,
+                    "eth_price": "1",
+                    "name": "Ether",
+                    "symbol": "ETH",
+                    "usd_price": "3000"
+                },
+                "quantity": 1,
+                "transaction": null
+            }),
+        ));
+        assert!(tracker.traits.is_empty());
+    }
+}